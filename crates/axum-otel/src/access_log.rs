@@ -0,0 +1,572 @@
+use crate::{
+    body_capture::{redact_form, DEFAULT_REDACT_KEYS},
+    metrics::body_size,
+};
+use axum::{
+    body::HttpBody,
+    extract::ConnectInfo,
+    http::{header, Method, Request, Response, Version},
+};
+use opentelemetry::trace::{TraceContextExt, TraceId};
+use pin_project_lite::pin_project;
+use std::{
+    fmt::{self, Write},
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tower::{Layer, Service};
+use tower_http::request_id::RequestId;
+use tracing::{field::display, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::{
+    dyn_event,
+    extract::fields::{self, TrustedProxies},
+};
+
+/// The target of the access log events, used to route them to a separate sink.
+pub const ACCESS_LOG_TARGET: &str = "access_log";
+
+/// The line format of the access log events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// The Apache common log format, followed by the latency in milliseconds, the trace
+    /// id and the request id.
+    Common,
+    /// The Apache combined log format, which adds the referer and user agent to the
+    /// common log format.
+    #[default]
+    Combined,
+    /// A JSON object with the same fields as the event, and the time in RFC 3339 format.
+    Json,
+}
+
+/// A [`Layer`] that emits one access log event per request.
+///
+/// The event is emitted under the [`ACCESS_LOG_TARGET`] target when the response is
+/// ready, with the formatted line as its message and the following fields:
+///
+/// - `remote_addr`: The client ip, see [`AccessLogLayer::trusted_proxies`]
+/// - `method`, `uri` and `version`: The request line, with the values of sensitive
+///   query parameters replaced, see [`AccessLogLayer::redact_query_keys`]
+/// - `status`: The response status code
+/// - `bytes`: The response body size, when known from the body or `Content-Length`
+/// - `referer` and `user_agent`: The request headers
+/// - `latency_ms`: The time until the response was ready
+/// - `trace_id` and `request_id`: To correlate the line with the request span
+///
+/// Filter on the target to write the access log to its own sink, for example with the
+/// `access_log=info` directive or [`tracing_subscriber::filter::Targets`].
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// [`tracing_subscriber::filter::Targets`]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/filter/targets/struct.Targets.html
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AccessLogFormat, AccessLogLayer, AxumOtelLayer};
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", get(handler)).layer(
+///     AxumOtelLayer::new().access_log(AccessLogLayer::new().format(AccessLogFormat::Json)),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct AccessLogLayer {
+    format: AccessLogFormat,
+    level: Level,
+    trusted_proxies: TrustedProxies,
+    redact_query_keys: Vec<String>,
+}
+
+impl Default for AccessLogLayer {
+    fn default() -> Self {
+        Self {
+            format: AccessLogFormat::default(),
+            level: Level::INFO,
+            trusted_proxies: TrustedProxies::default(),
+            redact_query_keys: DEFAULT_REDACT_KEYS.map(String::from).to_vec(),
+        }
+    }
+}
+
+impl AccessLogLayer {
+    /// Create a new `AccessLogLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`AccessLogFormat`] of the log lines.
+    ///
+    /// Defaults to [`AccessLogFormat::Combined`].
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the [`Level`] of the access log events.
+    ///
+    /// Defaults to [`Level::INFO`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the [`TrustedProxies`] whose forwarding headers are used to find the client ip.
+    ///
+    /// [`AxumOtelLayer`] replaces these with its own trusted proxies.
+    ///
+    /// [`AxumOtelLayer`]: crate::AxumOtelLayer
+    pub fn trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Redact the values of additional query parameters in the logged uri.
+    ///
+    /// Keys are matched case-insensitively. The same sensitive keys as
+    /// [`BodyCaptureLayer`], such as `password` and `token`, are always redacted.
+    ///
+    /// [`BodyCaptureLayer`]: crate::BodyCaptureLayer
+    pub fn redact_query_keys<I>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.redact_query_keys
+            .extend(keys.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLog<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLog {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that emits one access log event per request.
+///
+/// See [`AccessLogLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct AccessLog<S> {
+    inner: S,
+    layer: AccessLogLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AccessLog<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: HttpBody,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let record = AccessRecord::new(&request, &self.layer);
+        ResponseFuture {
+            inner: self.inner.call(request),
+            state: Some((record, Instant::now())),
+            format: self.layer.format,
+            level: self.layer.level,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`AccessLog`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        state: Option<(AccessRecord, Instant)>,
+        format: AccessLogFormat,
+        level: Level,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: HttpBody,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+
+        if let (Ok(response), Some((record, start))) = (&result, this.state.take()) {
+            let status = response.status().as_u16();
+            let bytes = body_size(response.body(), response.headers());
+            let latency = start.elapsed();
+            let line = record.format(*this.format, status, bytes, latency);
+            dyn_event!(
+                target: ACCESS_LOG_TARGET,
+                *this.level,
+                remote_addr = record.remote_addr.map(display),
+                method = %record.method,
+                uri = %record.uri,
+                version = ?record.version,
+                status,
+                bytes,
+                referer = record.referer.as_deref(),
+                user_agent = record.user_agent.as_deref(),
+                latency_ms = latency.as_millis() as u64,
+                trace_id = record.trace_id.map(display),
+                request_id = record.request_id.as_deref(),
+                "{}",
+                line
+            );
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+/// The request details of an access log line, taken when the request arrives.
+struct AccessRecord {
+    time: SystemTime,
+    remote_addr: Option<IpAddr>,
+    method: Method,
+    uri: String,
+    version: Version,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    trace_id: Option<TraceId>,
+    request_id: Option<String>,
+}
+
+impl AccessRecord {
+    fn new<B>(request: &Request<B>, layer: &AccessLogLayer) -> Self {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let span_context = Span::current().context().span().span_context().clone();
+        let header = |name| fields::extract_field_from_headers(request.headers(), name);
+
+        Self {
+            time: SystemTime::now(),
+            remote_addr: fields::extract_client_ip(request.headers(), peer, &layer.trusted_proxies),
+            method: request.method().clone(),
+            uri: match request.uri().query() {
+                Some(query) => format!(
+                    "{}?{}",
+                    request.uri().path(),
+                    redact_form(query, &layer.redact_query_keys)
+                ),
+                None => request.uri().path().to_owned(),
+            },
+            version: request.version(),
+            referer: header(header::REFERER).map(str::to_owned),
+            user_agent: header(header::USER_AGENT).map(str::to_owned),
+            trace_id: span_context.is_valid().then(|| span_context.trace_id()),
+            request_id: request
+                .extensions()
+                .get::<RequestId>()
+                .and_then(|id| id.header_value().to_str().ok())
+                .map(str::to_owned),
+        }
+    }
+
+    fn format(
+        &self,
+        format: AccessLogFormat,
+        status: u16,
+        bytes: Option<u64>,
+        latency: Duration,
+    ) -> String {
+        let mut line = String::new();
+        self.write(&mut line, format, status, bytes, latency)
+            .expect("writing to a String can't fail");
+        line
+    }
+
+    fn write(
+        &self,
+        out: &mut impl Write,
+        format: AccessLogFormat,
+        status: u16,
+        bytes: Option<u64>,
+        latency: Duration,
+    ) -> fmt::Result {
+        let latency_ms = latency.as_millis();
+        if format == AccessLogFormat::Json {
+            return write!(
+                out,
+                "{{\"time\":{},\"remote_addr\":{},\"method\":{},\"uri\":{},\"version\":{},\
+                 \"status\":{},\"bytes\":{},\"referer\":{},\"user_agent\":{},\
+                 \"latency_ms\":{},\"trace_id\":{},\"request_id\":{}}}",
+                Json(Some(Rfc3339Time(self.time))),
+                Json(self.remote_addr),
+                Json(Some(&self.method)),
+                Json(Some(&self.uri)),
+                Json(Some(format!("{:?}", self.version))),
+                status,
+                bytes.map_or_else(|| "null".to_owned(), |bytes| bytes.to_string()),
+                Json(self.referer.as_deref()),
+                Json(self.user_agent.as_deref()),
+                latency_ms,
+                Json(self.trace_id),
+                Json(self.request_id.as_deref()),
+            );
+        }
+
+        write!(
+            out,
+            "{} - - [{}] \"{} {} {:?}\" {} {}",
+            OrDash(self.remote_addr),
+            ClfTime(self.time),
+            self.method,
+            self.uri,
+            self.version,
+            status,
+            OrDash(bytes),
+        )?;
+        if format == AccessLogFormat::Combined {
+            write!(
+                out,
+                " \"{}\" \"{}\"",
+                Escaped(self.referer.as_deref().unwrap_or("-")),
+                Escaped(self.user_agent.as_deref().unwrap_or("-")),
+            )?;
+        }
+        write!(
+            out,
+            " {} {} {}",
+            latency_ms,
+            OrDash(self.trace_id),
+            OrDash(self.request_id.as_deref()),
+        )
+    }
+}
+
+/// Displays the value, or `-` when it is missing.
+struct OrDash<T>(Option<T>);
+
+impl<T: fmt::Display> fmt::Display for OrDash<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// Displays the value with quotes, backslashes and control characters escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Displays the value as a JSON string, or `null` when it is missing.
+struct Json<T>(Option<T>);
+
+impl<T: fmt::Display> fmt::Display for Json<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(value) => write!(f, "\"{}\"", Escaped(&value.to_string())),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+/// Displays the time in the common log format, e.g. `10/Oct/2000:13:55:36 +0000`.
+struct ClfTime(SystemTime);
+
+impl fmt::Display for ClfTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+
+        let (year, month, day, secs) = civil_time(self.0);
+        write!(
+            f,
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            secs / 3_600,
+            secs % 3_600 / 60,
+            secs % 60
+        )
+    }
+}
+
+/// Displays the time in the RFC 3339 format, e.g. `2000-10-10T13:55:36Z`.
+struct Rfc3339Time(SystemTime);
+
+impl fmt::Display for Rfc3339Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day, secs) = civil_time(self.0);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            secs / 3_600,
+            secs % 3_600 / 60,
+            secs % 60
+        )
+    }
+}
+
+/// Returns the UTC year, month, day and seconds of the day of the time.
+fn civil_time(time: SystemTime) -> (i64, i64, i64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Convert days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{EventCollector, SpanCollector},
+        AxumOtelLayer,
+    };
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn record() -> AccessRecord {
+        AccessRecord {
+            // 2000-10-10T13:55:36Z
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            remote_addr: Some(IpAddr::from([127, 0, 0, 1])),
+            method: Method::GET,
+            uri: "/apache_pb.gif?lang=en".to_owned(),
+            version: Version::HTTP_11,
+            referer: None,
+            user_agent: Some("curl/8.0 \"test\"".to_owned()),
+            trace_id: None,
+            request_id: Some("abc".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_combined_line() {
+        let line = record().format(
+            AccessLogFormat::Combined,
+            200,
+            Some(2326),
+            Duration::from_millis(12),
+        );
+        assert_eq!(
+            line,
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /apache_pb.gif?lang=en HTTP/1.1\" \
+             200 2326 \"-\" \"curl/8.0 \\\"test\\\"\" 12 - abc"
+        );
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = record().format(AccessLogFormat::Json, 404, None, Duration::from_millis(3));
+        assert_eq!(
+            line,
+            "{\"time\":\"2000-10-10T13:55:36Z\",\"remote_addr\":\"127.0.0.1\",\
+             \"method\":\"GET\",\"uri\":\"/apache_pb.gif?lang=en\",\"version\":\"HTTP/1.1\",\
+             \"status\":404,\"bytes\":null,\"referer\":null,\
+             \"user_agent\":\"curl/8.0 \\\"test\\\"\",\"latency_ms\":3,\"trace_id\":null,\
+             \"request_id\":\"abc\"}"
+        );
+    }
+
+    #[test]
+    fn test_query_is_redacted() {
+        let layer = AccessLogLayer::new().redact_query_keys(["Session"]);
+        let uri = |uri: &str| {
+            let request = Request::get(uri).body(()).unwrap();
+            AccessRecord::new(&request, &layer).uri
+        };
+
+        assert_eq!(
+            uri("/login?user=alice&password=hunter2&session=1"),
+            "/login?user=alice&password=[REDACTED]&session=[REDACTED]"
+        );
+        assert_eq!(
+            uri("/login?pass%77ord=hunter2&password+=hunter2"),
+            "/login?pass%77ord=[REDACTED]&password+=[REDACTED]"
+        );
+        assert_eq!(uri("/login"), "/login");
+    }
+
+    #[tokio::test]
+    async fn test_one_event_per_request() {
+        let events = EventCollector::new(ACCESS_LOG_TARGET);
+        let collector = SpanCollector::install_with(events.clone());
+        let app = Router::new()
+            .route("/", get(|| async { "Hello, world!" }))
+            .layer(AxumOtelLayer::new().access_log(AccessLogLayer::new()));
+
+        let request = Request::get("/?token=secret")
+            .header("x-request-id", "abc")
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let events = events.events();
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event["method"], "GET");
+        assert_eq!(event["uri"], "/?token=[REDACTED]");
+        assert_eq!(event["status"], "200");
+        assert_eq!(event["bytes"], "13");
+        assert_eq!(event["request_id"], "abc");
+        let spans = collector.spans();
+        assert_eq!(
+            event["trace_id"],
+            spans[0].span_context.trace_id().to_string()
+        );
+        assert!(event["message"].contains("\"GET /?token=[REDACTED] HTTP/1.1\" 200 13"));
+    }
+}
@@ -0,0 +1,830 @@
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{header, HeaderMap, Request, Response},
+    BoxError,
+};
+use http_body::Frame;
+use opentelemetry::KeyValue;
+use pin_project_lite::pin_project;
+use std::{
+    borrow::Cow,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::extract::fields;
+
+/// The name of the span event recording the request body.
+const REQUEST_BODY_EVENT: &str = "http.request.body";
+
+/// The name of the span event recording the response body.
+const RESPONSE_BODY_EVENT: &str = "http.response.body";
+
+/// The value recorded in place of a redacted body field.
+const REDACTED: &str = "[REDACTED]";
+
+/// The default maximum number of bytes captured per body.
+const DEFAULT_MAX_BYTES: usize = 4096;
+
+/// Content types captured by default.
+const DEFAULT_CONTENT_TYPES: [&str; 2] = ["application/json", "application/x-www-form-urlencoded"];
+
+/// Body fields that are always redacted.
+pub(crate) const DEFAULT_REDACT_KEYS: [&str; 6] = [
+    "password",
+    "secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "api_key",
+];
+
+#[derive(Clone, Debug)]
+struct CaptureConfig {
+    max_bytes: usize,
+    content_types: Vec<String>,
+    redact_keys: Vec<String>,
+    errors_only: bool,
+}
+
+/// A [`Layer`] that records the first bytes of request and response bodies as span events.
+///
+/// Up to [`BodyCaptureLayer::max_bytes`] of each body are copied while the body is
+/// streamed, so bodies are never buffered in full and streaming responses keep
+/// streaming. The captured bytes are recorded as the `http.request.body` and
+/// `http.response.body` events on the request span, with the
+/// `http.request.body.content` and `http.request.body.truncated` attributes (and the
+/// `http.response.body.*` equivalents).
+///
+/// Only bodies whose content type is in the allowlist are captured, by default JSON
+/// (including `+json` types) and URL encoded forms. The values of sensitive fields,
+/// such as `password` and `token`, are replaced with `[REDACTED]` before recording;
+/// this also applies to truncated JSON. Bodies of other content types added to the
+/// allowlist are recorded without redaction.
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::post, Router};
+/// use axum_otel::{AxumOtelLayer, BodyCaptureLayer};
+///
+/// async fn handler(body: String) -> String {
+///     body
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", post(handler)).layer(
+///     AxumOtelLayer::new().body_capture(
+///         BodyCaptureLayer::new()
+///             .max_bytes(1024)
+///             .redact_keys(["credit_card"])
+///             .errors_only(true),
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct BodyCaptureLayer {
+    config: Arc<CaptureConfig>,
+}
+
+impl BodyCaptureLayer {
+    /// Create a new `BodyCaptureLayer` capturing JSON and form bodies.
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(CaptureConfig {
+                max_bytes: DEFAULT_MAX_BYTES,
+                content_types: DEFAULT_CONTENT_TYPES.map(String::from).to_vec(),
+                redact_keys: DEFAULT_REDACT_KEYS.map(String::from).to_vec(),
+                errors_only: false,
+            }),
+        }
+    }
+
+    /// Set the maximum number of bytes captured per body.
+    ///
+    /// Defaults to 4096.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        Arc::make_mut(&mut self.config).max_bytes = max_bytes;
+        self
+    }
+
+    /// Replace the allowlist of captured content types, such as `application/json`.
+    ///
+    /// Parameters such as `charset` are ignored when matching. Allowing
+    /// `application/json` also allows `+json` types such as `application/problem+json`.
+    pub fn content_types<I>(mut self, content_types: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Arc::make_mut(&mut self.config).content_types = content_types
+            .into_iter()
+            .map(|content_type| content_type.into().to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Redact the values of additional JSON keys and form fields.
+    ///
+    /// Keys are matched case-insensitively, at any depth.
+    pub fn redact_keys<I>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Arc::make_mut(&mut self.config)
+            .redact_keys
+            .extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set whether bodies are only recorded for `4xx` and `5xx` responses.
+    ///
+    /// Defaults to `false`.
+    pub fn errors_only(mut self, errors_only: bool) -> Self {
+        Arc::make_mut(&mut self.config).errors_only = errors_only;
+        self
+    }
+}
+
+impl Default for BodyCaptureLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for BodyCaptureLayer {
+    type Service = BodyCapture<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyCapture {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that records the first bytes of request and response bodies as span events.
+///
+/// See [`BodyCaptureLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct BodyCapture<S> {
+    inner: S,
+    config: Arc<CaptureConfig>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for BodyCapture<S>
+where
+    S: Service<Request<Body>, Response = Response<ResBody>>,
+    ReqBody: HttpBody<Data = Bytes> + Send + 'static,
+    ReqBody::Error: Into<BoxError>,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let span = Span::current();
+        let request_capture = if span.is_none() {
+            None
+        } else {
+            body_kind(&self.config, request.headers()).map(|kind| {
+                (
+                    kind,
+                    Arc::new(Mutex::new(Captured::new(self.config.max_bytes))),
+                )
+            })
+        };
+        let request = request.map(|body| match &request_capture {
+            Some((_, captured)) => Body::new(CaptureBody {
+                inner: body,
+                captured: captured.clone(),
+                recorder: None,
+            }),
+            None => Body::new(body),
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            config: self.config.clone(),
+            span,
+            request_capture,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`BodyCapture`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        config: Arc<CaptureConfig>,
+        span: Span,
+        request_capture: Option<(BodyKind, Arc<Mutex<Captured>>)>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+
+        let status = response.status();
+        let record =
+            !this.config.errors_only || status.is_client_error() || status.is_server_error();
+        if !record || this.span.is_none() {
+            return Poll::Ready(Ok(response.map(Body::new)));
+        }
+
+        if let Some((kind, captured)) = this.request_capture.take() {
+            if let Ok(captured) = captured.lock() {
+                captured.record(REQUEST_BODY_EVENT, kind, this.config, this.span);
+            }
+        }
+
+        let Some(kind) = body_kind(this.config, response.headers()) else {
+            return Poll::Ready(Ok(response.map(Body::new)));
+        };
+        let captured = Arc::new(Mutex::new(Captured::new(this.config.max_bytes)));
+        let recorder = Recorder {
+            kind,
+            captured: captured.clone(),
+            config: this.config.clone(),
+            span: this.span.clone(),
+        };
+        Poll::Ready(Ok(response.map(|body| {
+            Body::new(CaptureBody {
+                inner: body,
+                captured,
+                recorder: Some(recorder),
+            })
+        })))
+    }
+}
+
+pin_project! {
+    /// A body that copies the first bytes of the inner body while it is streamed.
+    struct CaptureBody<B> {
+        #[pin]
+        inner: B,
+        captured: Arc<Mutex<Captured>>,
+        recorder: Option<Recorder>,
+    }
+}
+
+impl<B> HttpBody for CaptureBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            if let Ok(mut captured) = this.captured.lock() {
+                captured.push(data);
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Records the captured response body once the body is dropped.
+struct Recorder {
+    kind: BodyKind,
+    captured: Arc<Mutex<Captured>>,
+    config: Arc<CaptureConfig>,
+    span: Span,
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Ok(captured) = self.captured.lock() {
+            captured.record(RESPONSE_BODY_EVENT, self.kind, &self.config, &self.span);
+        }
+    }
+}
+
+/// The first bytes of a body.
+#[derive(Debug)]
+struct Captured {
+    bytes: Vec<u8>,
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl Captured {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            max_bytes,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        let remaining = self.max_bytes.saturating_sub(self.bytes.len());
+        if data.len() > remaining {
+            self.truncated = true;
+        }
+        self.bytes
+            .extend_from_slice(&data[..data.len().min(remaining)]);
+    }
+
+    fn record(&self, name: &'static str, kind: BodyKind, config: &CaptureConfig, span: &Span) {
+        if self.bytes.is_empty() {
+            return;
+        }
+        let content = String::from_utf8_lossy(&self.bytes);
+        let content = match kind {
+            BodyKind::Json => redact_json(&content, &config.redact_keys),
+            BodyKind::Form => redact_form(&content, &config.redact_keys),
+            BodyKind::Other => content.into_owned(),
+        };
+        span.add_event(
+            name,
+            vec![
+                KeyValue::new(format!("{}.content", name), content),
+                KeyValue::new(format!("{}.truncated", name), self.truncated),
+            ],
+        );
+    }
+}
+
+/// How a captured body is redacted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BodyKind {
+    Json,
+    Form,
+    Other,
+}
+
+/// Returns the [`BodyKind`] of the body, or `None` if its content type is not captured.
+fn body_kind(config: &CaptureConfig, headers: &HeaderMap) -> Option<BodyKind> {
+    let content_type = fields::extract_field_from_headers(headers, header::CONTENT_TYPE)?;
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let is_json = essence == "application/json" || essence.ends_with("+json");
+    let allowed = config
+        .content_types
+        .iter()
+        .any(|allowed| *allowed == essence || (allowed == "application/json" && is_json));
+    if !allowed {
+        None
+    } else if is_json {
+        Some(BodyKind::Json)
+    } else if essence == "application/x-www-form-urlencoded" {
+        Some(BodyKind::Form)
+    } else {
+        Some(BodyKind::Other)
+    }
+}
+
+/// Decode a URL encoded form key, so encoded keys such as `pass%77ord` are redacted too.
+///
+/// Surrounding whitespace, such as a `+` after the key, is trimmed.
+fn decode_form_key(key: &str) -> String {
+    let bytes = key.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match key
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).trim().to_owned()
+}
+
+/// Decode the escapes of a JSON object key, so escaped keys such as `pass\u0077ord` are
+/// redacted too.
+///
+/// Invalid escapes are kept as they are.
+fn decode_json_key(key: &str) -> Cow<'_, str> {
+    if !key.contains('\\') {
+        return Cow::Borrowed(key);
+    }
+    let mut decoded = String::with_capacity(key.len());
+    // Consecutive `\uXXXX` escapes, decoded together for surrogate pairs
+    let mut units = Vec::new();
+    let mut chars = key.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.as_str().starts_with('u') {
+            let hex = chars.as_str().get(1..5);
+            if let Some(unit) = hex.and_then(|hex| u16::from_str_radix(hex, 16).ok()) {
+                units.push(unit);
+                chars.nth(4);
+                continue;
+            }
+        }
+        decode_utf16(&mut units, &mut decoded);
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        decoded.push(match chars.next() {
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some(c) => c,
+            None => '\\',
+        });
+    }
+    decode_utf16(&mut units, &mut decoded);
+    Cow::Owned(decoded)
+}
+
+fn decode_utf16(units: &mut Vec<u16>, decoded: &mut String) {
+    decoded.extend(
+        char::decode_utf16(units.drain(..)).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+    );
+}
+
+fn is_redacted(key: &str, redact_keys: &[String]) -> bool {
+    redact_keys
+        .iter()
+        .any(|redact_key| redact_key.eq_ignore_ascii_case(key))
+}
+
+/// Redact the values of the given keys in a possibly truncated JSON document.
+///
+/// The document is scanned instead of parsed, so truncated documents are redacted too.
+fn redact_json(json: &str, redact_keys: &[String]) -> String {
+    let bytes = json.as_bytes();
+    let mut output = String::with_capacity(json.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'"' {
+            i += 1;
+            continue;
+        }
+        let Some(end) = skip_string(bytes, i) else {
+            break;
+        };
+        let key = &json[i + 1..end - 1];
+        let colon = skip_whitespace(bytes, end);
+        if colon >= bytes.len()
+            || bytes[colon] != b':'
+            || !is_redacted(&decode_json_key(key), redact_keys)
+        {
+            i = end;
+            continue;
+        }
+        let value_start = skip_whitespace(bytes, colon + 1);
+        let value_end = skip_value(bytes, value_start);
+        output.push_str(&json[copied..value_start]);
+        output.push('"');
+        output.push_str(REDACTED);
+        output.push('"');
+        copied = value_end;
+        i = value_end;
+    }
+    output.push_str(&json[copied..]);
+    output
+}
+
+/// Returns the index after the string starting at `start`, or `None` if it is truncated.
+fn skip_string(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn skip_whitespace(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Returns the index after the JSON value starting at `start`, or the end of the input.
+fn skip_value(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i = skip_string(bytes, i).unwrap_or(bytes.len());
+                if depth == 0 {
+                    return i;
+                }
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth == 0 => return i,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            b',' if depth == 0 => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Redact the values of the given fields in a URL encoded form.
+pub(crate) fn redact_form(form: &str, redact_keys: &[String]) -> String {
+    form.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_redacted(&decode_form_key(key), redact_keys) => {
+                Cow::Owned(format!("{}={}", key, REDACTED))
+            }
+            _ => Cow::Borrowed(pair),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::SpanCollector, AxumOtelLayer};
+    use axum::{
+        http::StatusCode,
+        routing::{get, post},
+        Router,
+    };
+    use futures_util::{stream, StreamExt};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::SpanData;
+    use std::convert::Infallible;
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+
+    fn keys() -> Vec<String> {
+        vec!["password".to_owned(), "card".to_owned()]
+    }
+
+    #[test]
+    fn test_redact_json() {
+        assert_eq!(
+            redact_json(r#"{"user":"alice","password":"hunter2"}"#, &keys()),
+            r#"{"user":"alice","password":"[REDACTED]"}"#
+        );
+        assert_eq!(
+            redact_json(
+                r#"{"Password": 42, "card": {"number": "4111"}, "note": "password"}"#,
+                &keys()
+            ),
+            r#"{"Password": "[REDACTED]", "card": "[REDACTED]", "note": "password"}"#
+        );
+        assert_eq!(
+            redact_json(r#"[{"password":"hun"#, &keys()),
+            r#"[{"password":"[REDACTED]""#
+        );
+        assert_eq!(
+            redact_json(
+                r#"{"pass\u0077ord":"hunter2","\u0063ard":1,"c\"ard":2}"#,
+                &keys()
+            ),
+            r#"{"pass\u0077ord":"[REDACTED]","\u0063ard":"[REDACTED]","c\"ard":2}"#
+        );
+    }
+
+    #[test]
+    fn test_decode_json_key() {
+        assert_eq!(decode_json_key("password"), "password");
+        assert_eq!(decode_json_key(r"pass\u0077ord"), "password");
+        assert_eq!(decode_json_key(r"\ud83d\ude00 \n\/"), "\u{1f600} \n/");
+        assert_eq!(decode_json_key(r"\u00"), "u00");
+    }
+
+    #[test]
+    fn test_redact_form() {
+        assert_eq!(
+            redact_form("user=alice&password=hunter2", &keys()),
+            "user=alice&password=[REDACTED]"
+        );
+        assert_eq!(
+            redact_form("pass%77ord=hunter2&password+=hunter2&card%=1", &keys()),
+            "pass%77ord=[REDACTED]&password+=[REDACTED]&card%=1"
+        );
+    }
+
+    #[test]
+    fn test_captured_truncates() {
+        let mut captured = Captured::new(4);
+        captured.push(b"abc");
+        captured.push(b"def");
+        assert_eq!(captured.bytes, b"abcd");
+        assert!(captured.truncated);
+    }
+
+    fn app(layer: BodyCaptureLayer) -> Router {
+        Router::new()
+            .route(
+                "/echo",
+                post(|request: Request<Body>| async move {
+                    let status = match request.uri().query() {
+                        Some("status=400") => StatusCode::BAD_REQUEST,
+                        _ => StatusCode::OK,
+                    };
+                    let content_type = request.headers()[header::CONTENT_TYPE].clone();
+                    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+                        .await
+                        .unwrap();
+                    (status, [(header::CONTENT_TYPE, content_type)], body)
+                }),
+            )
+            .layer(AxumOtelLayer::new().body_capture(layer))
+    }
+
+    async fn send(app: Router, uri: &str, content_type: &str, body: &'static str) -> Bytes {
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+    }
+
+    /// Returns the content of the body event, if the span has it.
+    fn body_event(span: &SpanData, name: &str) -> Option<Value> {
+        let event = span.events.iter().find(|event| event.name == name)?;
+        let key = format!("{}.content", name);
+        event
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| attribute.value.clone())
+    }
+
+    #[tokio::test]
+    async fn test_bodies_are_recorded_on_the_request_span() {
+        let collector = SpanCollector::install();
+        let body = r#"{"user":"alice","pass\u0077ord":"hunter2"}"#;
+        let response = send(
+            app(BodyCaptureLayer::new()),
+            "/echo",
+            "application/json",
+            body,
+        )
+        .await;
+        assert_eq!(response, body);
+
+        let spans = collector.spans();
+        let redacted = r#"{"user":"alice","pass\u0077ord":"[REDACTED]"}"#;
+        assert_eq!(
+            body_event(&spans[0], REQUEST_BODY_EVENT),
+            Some(redacted.into())
+        );
+        assert_eq!(
+            body_event(&spans[0], RESPONSE_BODY_EVENT),
+            Some(redacted.into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_only_skips_successful_responses() {
+        let collector = SpanCollector::install();
+        let app = app(BodyCaptureLayer::new().errors_only(true));
+        send(app.clone(), "/echo", "application/json", "{}").await;
+        send(app, "/echo?status=400", "application/json", "[]").await;
+
+        let spans = collector.spans();
+        assert_eq!(body_event(&spans[0], REQUEST_BODY_EVENT), None);
+        assert_eq!(body_event(&spans[0], RESPONSE_BODY_EVENT), None);
+        assert_eq!(body_event(&spans[1], REQUEST_BODY_EVENT), Some("[]".into()));
+        assert_eq!(
+            body_event(&spans[1], RESPONSE_BODY_EVENT),
+            Some("[]".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_content_types_are_skipped() {
+        let collector = SpanCollector::install();
+        send(app(BodyCaptureLayer::new()), "/echo", "text/plain", "hello").await;
+
+        let spans = collector.spans();
+        assert!(spans[0].events.iter().all(|event| {
+            event.name != REQUEST_BODY_EVENT && event.name != RESPONSE_BODY_EVENT
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_body_is_not_buffered() {
+        let collector = SpanCollector::install();
+        let (sender, receiver) = mpsc::unbounded_channel::<&'static str>();
+        let receiver = Arc::new(Mutex::new(Some(receiver)));
+        let app = Router::new()
+            .route(
+                "/stream",
+                get(move || async move {
+                    let receiver = receiver.lock().unwrap().take().unwrap();
+                    let chunks = stream::unfold(receiver, |mut receiver| async move {
+                        let chunk = receiver.recv().await?;
+                        Some((Ok::<_, Infallible>(chunk), receiver))
+                    });
+                    (
+                        [(header::CONTENT_TYPE, "application/json")],
+                        Body::from_stream(chunks),
+                    )
+                }),
+            )
+            .layer(AxumOtelLayer::new().body_capture(BodyCaptureLayer::new().max_bytes(4)));
+
+        let request = Request::get("/stream").body(Body::empty()).unwrap();
+        let mut body = app
+            .oneshot(request)
+            .await
+            .unwrap()
+            .into_body()
+            .into_data_stream();
+
+        // Each chunk reaches the client before the next one is produced
+        let mut received = Vec::new();
+        for chunk in ["[1,", "2,3", "]"] {
+            sender.send(chunk).unwrap();
+            received.extend_from_slice(&body.next().await.unwrap().unwrap());
+        }
+        drop(sender);
+        assert!(body.next().await.is_none());
+        assert_eq!(received, b"[1,2,3]");
+        drop(body);
+
+        let spans = collector.spans();
+        let event = spans[0]
+            .events
+            .iter()
+            .find(|event| event.name == RESPONSE_BODY_EVENT)
+            .unwrap();
+        assert_eq!(
+            event.attributes,
+            [
+                KeyValue::new("http.response.body.content", "[1,2"),
+                KeyValue::new("http.response.body.truncated", true),
+            ]
+        );
+    }
+}
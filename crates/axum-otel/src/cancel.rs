@@ -0,0 +1,291 @@
+use crate::SpanStatus;
+use axum::http::Request;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::{dyn_event, extract::schema};
+
+/// A [`Layer`] that records requests cancelled before the response was ready.
+///
+/// When a client disconnects mid-request, the server drops the response future, so
+/// neither [`AxumOtelOnResponse`] nor [`AxumOtelOnFailure`] runs. This layer detects the
+/// dropped future and records the following on the request span:
+///
+/// - `http.request.cancelled`: `true`
+/// - `http.request.cancelled_after_ms`: The time from the request to the cancellation
+/// - `otel.status_code`: The configured [`SpanStatus`], `UNSET` by default so client
+///   aborts are not counted as server errors
+/// - `otel.status_message`: `request cancelled`, only when the status is
+///   [`SpanStatus::Error`] since recording a status message marks the span as an error
+///
+/// A "request cancelled" event is emitted as well. Futures dropped while unwinding from a
+/// panic are not recorded as cancelled. Responses whose body is dropped
+/// while streaming are recorded by [`AxumOtelOnBodyChunk`] instead.
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+/// [`AxumOtelOnFailure`]: crate::AxumOtelOnFailure
+/// [`AxumOtelOnBodyChunk`]: crate::AxumOtelOnBodyChunk
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelLayer, CancellationLayer, Level, SpanStatus};
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", get(handler)).layer(
+///     AxumOtelLayer::new().cancellation(
+///         CancellationLayer::new()
+///             .level(Level::WARN)
+///             .status(SpanStatus::Error),
+///     ),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CancellationLayer {
+    level: Level,
+    status: SpanStatus,
+}
+
+impl Default for CancellationLayer {
+    fn default() -> Self {
+        Self {
+            level: Level::INFO,
+            status: SpanStatus::Unset,
+        }
+    }
+}
+
+impl CancellationLayer {
+    /// Create a new `CancellationLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Level`] used for the "request cancelled" event.
+    ///
+    /// Defaults to [`Level::INFO`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the [`SpanStatus`] of cancelled requests.
+    ///
+    /// Defaults to [`SpanStatus::Unset`].
+    pub fn status(mut self, status: SpanStatus) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+impl<S> Layer<S> for CancellationLayer {
+    type Service = DetectCancellation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DetectCancellation {
+            inner,
+            layer: *self,
+        }
+    }
+}
+
+/// Middleware that records requests cancelled before the response was ready.
+///
+/// See [`CancellationLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct DetectCancellation<S> {
+    inner: S,
+    layer: CancellationLayer,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for DetectCancellation<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let span = Span::current();
+        let guard = (!span.is_none()).then(|| CancelGuard {
+            span,
+            layer: self.layer,
+            start: Instant::now(),
+            completed: false,
+        });
+        ResponseFuture {
+            inner: self.inner.call(request),
+            guard,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`DetectCancellation`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        guard: Option<CancelGuard>,
+    }
+}
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.inner.poll(cx));
+        if let Some(guard) = this.guard {
+            guard.completed = true;
+        }
+        Poll::Ready(output)
+    }
+}
+
+/// Records the cancellation when dropped before the response future completed.
+struct CancelGuard {
+    span: Span,
+    layer: CancellationLayer,
+    start: Instant,
+    completed: bool,
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        // A panicking handler is a server bug, not a client abort
+        if self.completed || std::thread::panicking() {
+            return;
+        }
+
+        let elapsed = self.start.elapsed();
+        let span = &self.span;
+        span.set_attribute("http.request.cancelled", true);
+        span.set_attribute(
+            "http.request.cancelled_after_ms",
+            elapsed.as_millis() as i64,
+        );
+        if let Some(status_code) = self.layer.status.as_otel_status_code() {
+            span.record(schema::OTEL_STATUS_CODE, status_code);
+        }
+        if self.layer.status == SpanStatus::Error {
+            span.record(schema::OTEL_STATUS_MESSAGE, "request cancelled");
+        }
+        span.in_scope(|| {
+            dyn_event!(
+                self.layer.level,
+                elapsed = elapsed.as_millis() as u64,
+                "request cancelled"
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{attribute, SpanCollector};
+    use opentelemetry::trace::Status;
+    use std::{convert::Infallible, time::Duration};
+    use tower::{service_fn, ServiceExt};
+    use tracing::Instrument;
+    use tracing_otel_extra::request_span;
+
+    /// Sends a completed request and a cancelled one through the layer.
+    async fn cancel_request(layer: CancellationLayer) {
+        let service = layer.layer(service_fn(|request: Request<()>| async move {
+            if request.uri().path() == "/slow" {
+                std::future::pending::<()>().await;
+            }
+            Ok::<_, Infallible>(())
+        }));
+
+        let request = Request::get("/").body(()).unwrap();
+        service
+            .clone()
+            .oneshot(request)
+            .instrument(request_span!(Level::INFO, "completed"))
+            .await
+            .unwrap();
+
+        let request = Request::get("/slow").body(()).unwrap();
+        let future = service
+            .oneshot(request)
+            .instrument(request_span!(Level::INFO, "cancelled"));
+        let result = tokio::time::timeout(Duration::from_millis(10), future).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_future_is_recorded() {
+        let collector = SpanCollector::install();
+        cancel_request(CancellationLayer::new()).await;
+
+        let spans = collector.spans();
+        let completed = spans.iter().find(|span| span.name == "completed").unwrap();
+        assert!(completed.events.is_empty());
+        assert_eq!(attribute(completed, "http.request.cancelled"), None);
+
+        let cancelled = spans.iter().find(|span| span.name == "cancelled").unwrap();
+        let events: Vec<_> = cancelled.events.iter().map(|event| &event.name).collect();
+        assert_eq!(events, ["request cancelled"]);
+        assert_eq!(
+            attribute(cancelled, "http.request.cancelled"),
+            Some(true.into())
+        );
+        // Client aborts are not server errors by default
+        assert_eq!(cancelled.status, Status::Unset);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_recorded() {
+        let collector = SpanCollector::install();
+        cancel_request(CancellationLayer::new().status(SpanStatus::Error)).await;
+
+        let spans = collector.spans();
+        let cancelled = spans.iter().find(|span| span.name == "cancelled").unwrap();
+        assert_eq!(cancelled.status, Status::error("request cancelled"));
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_not_recorded_as_cancelled() {
+        let collector = SpanCollector::install();
+        let service = CancellationLayer::new().layer(service_fn(|_: Request<()>| async move {
+            panic!("handler panicked");
+            #[allow(unreachable_code)]
+            Ok::<_, Infallible>(())
+        }));
+
+        let request = Request::get("/").body(()).unwrap();
+        let future = service
+            .oneshot(request)
+            .instrument(request_span!(Level::INFO, "panicked"));
+        // The current thread runtime polls the task on this thread, with the collector
+        assert!(tokio::spawn(future).await.unwrap_err().is_panic());
+
+        let spans = collector.spans();
+        let panicked = spans.iter().find(|span| span.name == "panicked").unwrap();
+        assert!(panicked.events.is_empty());
+        assert_eq!(attribute(panicked, "http.request.cancelled"), None);
+    }
+}
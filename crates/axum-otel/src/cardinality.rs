@@ -0,0 +1,114 @@
+use opentelemetry::{metrics::Counter, Key, KeyValue, Value};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+/// The value that replaces attribute values once the limit is reached.
+pub(crate) const OVERFLOW_VALUE: &str = "__other__";
+
+/// Caps the number of distinct values of each string attribute of one instrument.
+///
+/// Values are admitted until the limit is reached, later values are replaced with
+/// [`OVERFLOW_VALUE`] and counted by the `axum_otel.cardinality.overflow` counter.
+/// Numeric and boolean attributes, such as `http.response.status_code`, have a bounded
+/// set of values and are never replaced, so each attribute keeps a single type.
+#[derive(Debug)]
+pub(crate) struct CardinalityLimiter {
+    metric: &'static str,
+    max_values: usize,
+    seen: RwLock<HashMap<Key, HashSet<String>>>,
+    overflow: Counter<u64>,
+}
+
+impl CardinalityLimiter {
+    /// Create a limiter for the attributes of the `metric` instrument.
+    pub(crate) fn new(metric: &'static str, max_values: usize, overflow: Counter<u64>) -> Self {
+        Self {
+            metric,
+            max_values,
+            seen: RwLock::default(),
+            overflow,
+        }
+    }
+
+    /// Returns the attributes with the values over the limit replaced with
+    /// [`OVERFLOW_VALUE`].
+    pub(crate) fn limit<'a>(&self, attributes: &'a [KeyValue]) -> Cow<'a, [KeyValue]> {
+        // Most requests only carry values that were already admitted
+        if self.all_seen(attributes) {
+            return Cow::Borrowed(attributes);
+        }
+
+        let mut limited = Cow::Borrowed(attributes);
+        let mut seen = self.seen.write().unwrap_or_else(|error| error.into_inner());
+        for (index, attribute) in attributes.iter().enumerate() {
+            let Value::String(value) = &attribute.value else {
+                continue;
+            };
+            let values = seen.entry(attribute.key.clone()).or_default();
+            if values.contains(value.as_str()) {
+                continue;
+            }
+            if values.len() < self.max_values {
+                values.insert(value.as_str().to_owned());
+                continue;
+            }
+
+            self.overflow.add(
+                1,
+                &[
+                    KeyValue::new("metric", self.metric),
+                    KeyValue::new("attribute", attribute.key.as_str().to_owned()),
+                ],
+            );
+            limited.to_mut()[index].value = Value::from(OVERFLOW_VALUE);
+        }
+        limited
+    }
+
+    fn all_seen(&self, attributes: &[KeyValue]) -> bool {
+        let seen = self.seen.read().unwrap_or_else(|error| error.into_inner());
+        attributes.iter().all(|attribute| match &attribute.value {
+            Value::String(value) => seen
+                .get(&attribute.key)
+                .is_some_and(|values| values.contains(value.as_str())),
+            _ => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::global;
+
+    fn limiter(max_values: usize) -> CardinalityLimiter {
+        let overflow = global::meter("test").u64_counter("overflow").build();
+        CardinalityLimiter::new("http.server.request.duration", max_values, overflow)
+    }
+
+    #[test]
+    fn test_values_over_the_limit_overflow() {
+        let limiter = limiter(2);
+        let route = |route: &'static str| {
+            let attributes = [KeyValue::new("http.route", route)];
+            limiter.limit(&attributes)[0].value.as_str().into_owned()
+        };
+
+        assert_eq!(route("/a"), "/a");
+        assert_eq!(route("/b"), "/b");
+        assert_eq!(route("/c"), OVERFLOW_VALUE);
+        assert_eq!(route("/a"), "/a");
+    }
+
+    #[test]
+    fn test_numeric_values_are_not_limited() {
+        let limiter = limiter(1);
+        for status in [200, 404, 500] {
+            let attributes = [KeyValue::new("http.response.status_code", status)];
+            assert_eq!(limiter.limit(&attributes)[0].value, Value::I64(status));
+        }
+    }
+}
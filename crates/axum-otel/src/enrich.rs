@@ -0,0 +1,225 @@
+use crate::{RequestInfo, ResponseInfo};
+use opentelemetry::{Key, Value};
+use std::{fmt, sync::Arc};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// The extra fields declared on every request span, which enrichment callbacks can
+/// record like the built-in fields.
+///
+/// The set is fixed by design: `tracing` declares the fields of a span at compile time
+/// and limits them to 32, so slots can't be named or added at runtime. Generic slots
+/// mapped to names at runtime don't work either, since `tracing-opentelemetry` exports
+/// every recorded span field under the name of the slot. Enrichment callbacks can
+/// record attributes with any other key as well, they are set on the OpenTelemetry
+/// span directly, see [`SpanAttributes`].
+///
+/// | Field                  | Description                              |
+/// |------------------------|------------------------------------------|
+/// | `enduser.id`           | The authenticated user                   |
+/// | `tenant.id`            | The tenant the request belongs to        |
+/// | `api.version`          | The API version requested by the client  |
+/// | `feature_flag.variant` | The feature-flag variant serving the request |
+pub const ENRICHMENT_FIELDS: [&str; 4] = [
+    "enduser.id",
+    "tenant.id",
+    "api.version",
+    "feature_flag.variant",
+];
+
+/// Records extra attributes on a request span from an enrichment callback.
+///
+/// Attributes in [`ENRICHMENT_FIELDS`] are recorded as span fields, so they show up in
+/// formatted logs as well. Attributes with any other key are set on the OpenTelemetry
+/// span directly, so they are exported but not included in formatted logs.
+#[derive(Debug)]
+pub struct SpanAttributes<'a> {
+    span: &'a tracing::Span,
+}
+
+impl<'a> SpanAttributes<'a> {
+    pub(crate) fn new(span: &'a tracing::Span) -> Self {
+        Self { span }
+    }
+
+    /// Record the attribute on the request span.
+    pub fn record(&mut self, key: impl Into<Key>, value: impl Into<Value>) -> &mut Self {
+        let key = key.into();
+        let value = value.into();
+        if !ENRICHMENT_FIELDS.contains(&key.as_str()) {
+            self.span.set_attribute(key, value);
+            return self;
+        }
+        match &value {
+            Value::Bool(value) => self.span.record(key.as_str(), *value),
+            Value::I64(value) => self.span.record(key.as_str(), *value),
+            Value::F64(value) => self.span.record(key.as_str(), *value),
+            Value::String(value) => self.span.record(key.as_str(), value.as_str()),
+            _ => self
+                .span
+                .record(key.as_str(), tracing::field::display(&value)),
+        };
+        self
+    }
+}
+
+type RequestCallback = Arc<dyn Fn(&RequestInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync>;
+type ResponseCallback = Arc<dyn Fn(&ResponseInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync>;
+
+/// The enrichment callbacks of the span creator.
+#[derive(Clone, Default)]
+pub(crate) struct RequestEnrichers(Arc<[RequestCallback]>);
+
+impl RequestEnrichers {
+    pub(crate) fn push(&mut self, callback: RequestCallback) {
+        self.0 = self.0.iter().cloned().chain([callback]).collect();
+    }
+
+    pub(crate) fn enrich(&self, request: &RequestInfo<'_>, span: &tracing::Span) {
+        let mut attributes = SpanAttributes::new(span);
+        for callback in self.0.iter() {
+            callback(request, &mut attributes);
+        }
+    }
+}
+
+impl fmt::Debug for RequestEnrichers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequestEnrichers")
+            .field(&self.0.len())
+            .finish()
+    }
+}
+
+/// The enrichment callbacks of the response handler.
+#[derive(Clone, Default)]
+pub(crate) struct ResponseEnrichers(Arc<[ResponseCallback]>);
+
+impl ResponseEnrichers {
+    pub(crate) fn push(&mut self, callback: ResponseCallback) {
+        self.0 = self.0.iter().cloned().chain([callback]).collect();
+    }
+
+    pub(crate) fn enrich(&self, response: &ResponseInfo<'_>, span: &tracing::Span) {
+        let mut attributes = SpanAttributes::new(span);
+        for callback in self.0.iter() {
+            callback(response, &mut attributes);
+        }
+    }
+}
+
+impl fmt::Debug for ResponseEnrichers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ResponseEnrichers")
+            .field(&self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelOnResponse, AxumOtelSpanCreator,
+    };
+    use axum::http::{Request, Response};
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tower_http::trace::{MakeSpan, OnResponse};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    #[derive(Clone)]
+    struct Tenant(&'static str);
+
+    /// Collects the output of a fmt layer.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Output {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn enrich() {
+        let mut make_span = AxumOtelSpanCreator::new().enrich(|request, attributes| {
+            if let Some(Tenant(tenant)) = request.extensions().get::<Tenant>() {
+                attributes
+                    .record("tenant.id", *tenant)
+                    .record("tenant.tier", "gold");
+            }
+        });
+        let on_response = AxumOtelOnResponse::new().enrich(|response, attributes| {
+            attributes.record(
+                "feature_flag.variant",
+                response.status().as_str().to_owned(),
+            );
+        });
+
+        let request = Request::get("/")
+            .extension(Tenant("acme"))
+            .body(())
+            .unwrap();
+        let span = make_span.make_span(&request);
+        span.in_scope(|| {
+            on_response.on_response(&Response::new(()), Duration::ZERO, &span);
+        });
+    }
+
+    #[test]
+    fn test_enrichers_record_on_the_request_span() {
+        let collector = SpanCollector::install();
+        enrich();
+
+        let span = &collector.spans()[0];
+        assert_eq!(attribute(span, "tenant.id"), Some("acme".into()));
+        assert_eq!(attribute(span, "tenant.tier"), Some("gold".into()));
+        assert_eq!(attribute(span, "feature_flag.variant"), Some("200".into()));
+        // Every attribute is exported once, under its own name
+        let keys: Vec<_> = span
+            .attributes
+            .iter()
+            .map(|attribute| attribute.key.as_str())
+            .collect();
+        assert!(
+            !keys.iter().any(|key| key.starts_with("enrich.")),
+            "{:?}",
+            keys
+        );
+        assert_eq!(keys.iter().filter(|key| **key == "tenant.id").count(), 1);
+    }
+
+    #[test]
+    fn test_enrichment_fields_reach_formatted_logs() {
+        let output = Output::default();
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(output.clone())
+            .with_ansi(false);
+        let _collector = SpanCollector::install_with(layer);
+        enrich();
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("tenant.id=\"acme\""), "{}", output);
+        assert!(
+            output.contains("feature_flag.variant=\"200\""),
+            "{}",
+            output
+        );
+        // Attributes outside the enrichment fields are only exported
+        assert!(!output.contains("tenant.tier"), "{}", output);
+    }
+}
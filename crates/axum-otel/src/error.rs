@@ -0,0 +1,263 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
+};
+use opentelemetry::KeyValue;
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    convert::Infallible,
+    error::Error,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// A report of a handler error, recorded on the request span as an `exception` event.
+///
+/// Insert the report into the response extensions, usually from an [`IntoResponse`]
+/// implementation, and [`AxumOtelOnResponse`] adds an `exception` event with the
+/// following attributes to the request span:
+///
+/// - `exception.type`: The type name of the error, or `_OTHER` for trait objects like
+///   [`BoxError`] whose concrete type is not known
+/// - `exception.message`: The error message followed by its [`Error::source`] chain,
+///   separated by `: `
+/// - `exception.stacktrace`: The backtrace, when captured
+///
+/// The report can also be returned from a handler, as in
+/// `(StatusCode::INTERNAL_SERVER_ERROR, ErrorReport::new(&error))`.
+///
+/// The report is independent of the error type, so the error itself does not need
+/// to be `Clone` or `'static`.
+///
+/// [`IntoResponse`]: axum::response::IntoResponse
+/// [`BoxError`]: axum::BoxError
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{
+///     http::StatusCode,
+///     response::{IntoResponse, Response},
+/// };
+/// use axum_otel::ErrorReport;
+///
+/// #[derive(Debug)]
+/// struct AppError(std::io::Error);
+///
+/// impl std::fmt::Display for AppError {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         write!(f, "failed to load the article")
+///     }
+/// }
+///
+/// impl std::error::Error for AppError {
+///     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+///         Some(&self.0)
+///     }
+/// }
+///
+/// impl IntoResponse for AppError {
+///     fn into_response(self) -> Response {
+///         let report = ErrorReport::new(&self).capture_backtrace();
+///         (StatusCode::INTERNAL_SERVER_ERROR, report, "internal server error").into_response()
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ErrorReport {
+    error_type: String,
+    message: String,
+    stacktrace: Option<String>,
+}
+
+impl ErrorReport {
+    /// Create a new `ErrorReport` from the error and its source chain.
+    pub fn new<E>(error: &E) -> Self
+    where
+        E: Error + ?Sized,
+    {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        let type_name = std::any::type_name::<E>();
+        // A trait object, like `BoxError`, does not name the type of the error
+        let error_type = if type_name.starts_with("dyn ") {
+            "_OTHER"
+        } else {
+            type_name
+        };
+        Self {
+            error_type: error_type.to_owned(),
+            message,
+            stacktrace: None,
+        }
+    }
+
+    /// Record the backtrace as `exception.stacktrace`, if it was captured.
+    pub fn backtrace(mut self, backtrace: &Backtrace) -> Self {
+        if backtrace.status() == BacktraceStatus::Captured {
+            self.stacktrace = Some(backtrace.to_string());
+        }
+        self
+    }
+
+    /// Capture a backtrace at the call site and record it as `exception.stacktrace`.
+    ///
+    /// Like [`Backtrace::capture`], this only captures a backtrace when the
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables enable it.
+    pub fn capture_backtrace(self) -> Self {
+        self.backtrace(&Backtrace::capture())
+    }
+
+    /// The type name of the error.
+    pub fn error_type(&self) -> &str {
+        &self.error_type
+    }
+
+    /// The error message followed by its source chain.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The captured backtrace.
+    pub fn stacktrace(&self) -> Option<&str> {
+        self.stacktrace.as_deref()
+    }
+
+    /// Add the `exception` event to the span.
+    pub(crate) fn record(&self, span: &tracing::Span) {
+        let mut attributes = vec![
+            KeyValue::new("exception.type", self.error_type.clone()),
+            KeyValue::new("exception.message", self.message.clone()),
+        ];
+        if let Some(stacktrace) = &self.stacktrace {
+            attributes.push(KeyValue::new("exception.stacktrace", stacktrace.clone()));
+        }
+        span.add_event("exception", attributes);
+    }
+}
+
+impl IntoResponseParts for ErrorReport {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+/// Responds with `500 Internal Server Error` and an empty body, so a handler can return
+/// the report on its own or after a status code, as in `(StatusCode::BAD_GATEWAY, report)`.
+impl IntoResponse for ErrorReport {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self, ()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::SpanCollector, AxumOtelRouterExt};
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use opentelemetry::Value;
+    use std::fmt;
+    use tower::ServiceExt;
+
+    #[derive(Debug)]
+    struct LoadError(fmt::Error);
+
+    impl fmt::Display for LoadError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "failed to load")
+        }
+    }
+
+    impl Error for LoadError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_error_report_source_chain() {
+        let report = ErrorReport::new(&LoadError(fmt::Error));
+        assert!(report.error_type().ends_with("LoadError"));
+        assert_eq!(
+            report.message(),
+            "failed to load: an error occurred when formatting an argument"
+        );
+        assert!(report.stacktrace().is_none());
+    }
+
+    #[test]
+    fn test_error_report_of_trait_object() {
+        let error: axum::BoxError = Box::new(LoadError(fmt::Error));
+        let report = ErrorReport::new(error.as_ref());
+        assert_eq!(report.error_type(), "_OTHER");
+        assert_eq!(
+            report.message(),
+            "failed to load: an error occurred when formatting an argument"
+        );
+        let error: axum::BoxError = std::io::Error::other("connection reset").into();
+        assert_eq!(ErrorReport::new(error.as_ref()).error_type(), "_OTHER");
+    }
+
+    #[tokio::test]
+    async fn test_error_report_is_recorded_as_exception_event() {
+        async fn handler() -> impl IntoResponse {
+            let error = LoadError(fmt::Error);
+            (StatusCode::INTERNAL_SERVER_ERROR, ErrorReport::new(&error))
+        }
+
+        async fn backtrace_handler() -> impl IntoResponse {
+            let error = LoadError(fmt::Error);
+            let report = ErrorReport::new(&error).backtrace(&Backtrace::force_capture());
+            (StatusCode::INTERNAL_SERVER_ERROR, report)
+        }
+
+        let collector = SpanCollector::install();
+        let app = Router::new()
+            .route("/", get(handler))
+            .route("/backtrace", get(backtrace_handler))
+            .with_otel();
+        for uri in ["/", "/backtrace"] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let spans = collector.spans();
+        let exception = |index: usize| {
+            let event = spans[index]
+                .events
+                .iter()
+                .find(|event| event.name == "exception")
+                .unwrap();
+            move |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key.as_str() == key)
+                    .map(|attribute| attribute.value.clone())
+            }
+        };
+
+        let attribute = exception(0);
+        let Some(Value::String(error_type)) = attribute("exception.type") else {
+            panic!("missing exception.type");
+        };
+        assert!(error_type.as_str().ends_with("LoadError"));
+        assert_eq!(
+            attribute("exception.message"),
+            Some("failed to load: an error occurred when formatting an argument".into())
+        );
+        assert_eq!(attribute("exception.stacktrace"), None);
+
+        let attribute = exception(1);
+        assert!(attribute("exception.stacktrace").is_some());
+    }
+}
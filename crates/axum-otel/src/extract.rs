@@ -0,0 +1,328 @@
+use crate::trace_response::format_traceparent;
+use axum::{
+    extract::{FromRequestParts, MatchedPath, OptionalFromRequestParts},
+    http::{self, request::Parts, Request, StatusCode},
+    response::{IntoResponse, Response},
+};
+use opentelemetry::trace::{self, SpanContext, TraceContextExt};
+use pin_project_lite::pin_project;
+use std::{
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// The OpenTelemetry context of the request span.
+///
+/// The context is stored in the request extensions by [`OtelContextLayer`], which
+/// [`AxumOtelLayer`] applies, so it does not depend on which span is current when the
+/// handler runs. Use it to continue the trace in spawned work.
+///
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::post, Router};
+/// use axum_otel::{AxumOtelRouterExt, OtelContext};
+/// use tracing_opentelemetry::OpenTelemetrySpanExt;
+///
+/// async fn handler(OtelContext(context): OtelContext) -> &'static str {
+///     tokio::spawn(async move {
+///         let span = tracing::info_span!("send_welcome_email");
+///         span.set_parent(context);
+///         let _guard = span.enter();
+///         // ...
+///     });
+///     "queued"
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", post(handler)).with_otel();
+/// ```
+#[derive(Clone, Debug)]
+pub struct OtelContext(pub opentelemetry::Context);
+
+impl OtelContext {
+    fn from_parts(parts: &Parts) -> Result<&Self, TraceContextRejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .ok_or(TraceContextRejection::MissingContext)
+    }
+
+    /// The span context of the request span, if it is valid.
+    fn span_context(parts: &Parts) -> Result<SpanContext, TraceContextRejection> {
+        let span_context = Self::from_parts(parts)?.0.span().span_context().clone();
+        if span_context.is_valid() {
+            Ok(span_context)
+        } else {
+            Err(TraceContextRejection::InvalidSpanContext)
+        }
+    }
+}
+
+/// The trace id of the request span.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelRouterExt, TraceId};
+///
+/// async fn handler(TraceId(trace_id): TraceId) -> String {
+///     format!("trace id: {}", trace_id)
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", get(handler)).with_otel();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceId(pub trace::TraceId);
+
+/// The span id of the request span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpanId(pub trace::SpanId);
+
+/// The W3C `traceparent` of the request span, e.g.
+/// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceParent(pub String);
+
+impl<S> FromRequestParts<S> for OtelContext
+where
+    S: Send + Sync,
+{
+    type Rejection = TraceContextRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_parts(parts).cloned()
+    }
+}
+
+impl<S> OptionalFromRequestParts<S> for OtelContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(Self::from_parts(parts).ok().cloned())
+    }
+}
+
+macro_rules! impl_from_span_context {
+    ($ty:ident, |$span_context:ident| $value:expr) => {
+        impl<S> FromRequestParts<S> for $ty
+        where
+            S: Send + Sync,
+        {
+            type Rejection = TraceContextRejection;
+
+            async fn from_request_parts(
+                parts: &mut Parts,
+                _state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                let $span_context = OtelContext::span_context(parts)?;
+                Ok($ty($value))
+            }
+        }
+
+        impl<S> OptionalFromRequestParts<S> for $ty
+        where
+            S: Send + Sync,
+        {
+            type Rejection = Infallible;
+
+            async fn from_request_parts(
+                parts: &mut Parts,
+                _state: &S,
+            ) -> Result<Option<Self>, Self::Rejection> {
+                Ok(OtelContext::span_context(parts)
+                    .ok()
+                    .map(|$span_context| $ty($value)))
+            }
+        }
+    };
+}
+
+impl_from_span_context!(TraceId, |span_context| span_context.trace_id());
+impl_from_span_context!(SpanId, |span_context| span_context.span_id());
+impl_from_span_context!(TraceParent, |span_context| format_traceparent(
+    &span_context
+));
+
+/// Rejection used for the trace context extractors.
+///
+/// Responds with `500 Internal Server Error`, as both cases are server
+/// misconfigurations. Use `Option<T>` to make the extractors optional.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TraceContextRejection {
+    /// The request extensions contain no [`OtelContext`], because [`OtelContextLayer`]
+    /// is not applied.
+    MissingContext,
+    /// The request span has no valid span context, because it is disabled or skipped.
+    InvalidSpanContext,
+}
+
+impl fmt::Display for TraceContextRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingContext => write!(
+                f,
+                "Missing OpenTelemetry context. Is `AxumOtelLayer` applied to the router?"
+            ),
+            Self::InvalidSpanContext => write!(f, "The request span is not sampled or traced"),
+        }
+    }
+}
+
+impl std::error::Error for TraceContextRejection {}
+
+impl IntoResponse for TraceContextRejection {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+    }
+}
+
+/// A [`Layer`] that stores the [`OtelContext`] of the request span in the request
+/// extensions.
+///
+/// It also copies the [`MatchedPath`] of the request into the response extensions, so
+/// [`AxumOtelOnResponse`] can apply per-route settings.
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OtelContextLayer {
+    _priv: (),
+}
+
+impl OtelContextLayer {
+    /// Create a new `OtelContextLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for OtelContextLayer {
+    type Service = InsertOtelContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InsertOtelContext { inner }
+    }
+}
+
+/// Middleware that stores the [`OtelContext`] of the request span in the request
+/// extensions.
+///
+/// See [`OtelContextLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct InsertOtelContext<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for InsertOtelContext<S>
+where
+    S: Service<Request<ReqBody>, Response = http::Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let context = Span::current().context();
+        request.extensions_mut().insert(OtelContext(context));
+        let matched_path = request.extensions().get::<MatchedPath>().cloned();
+        ResponseFuture {
+            inner: self.inner.call(request),
+            matched_path,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`InsertOtelContext`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        matched_path: Option<MatchedPath>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<http::Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut result = ready!(this.inner.poll(cx));
+        if let (Ok(response), Some(matched_path)) = (&mut result, this.matched_path.take()) {
+            response.extensions_mut().insert(matched_path);
+        }
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::SpanCollector, AxumOtelRouterExt};
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn get_body(app: Router, uri: &str) -> (StatusCode, String) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_trace_context_extractors() {
+        let collector = SpanCollector::install();
+
+        let app = Router::new()
+            .route(
+                "/trace-id",
+                get(|TraceId(trace_id): TraceId| async move { trace_id.to_string() }),
+            )
+            .route(
+                "/traceparent",
+                get(|TraceParent(traceparent): TraceParent| async move { traceparent }),
+            );
+
+        let (status, trace_id) = get_body(app.clone().with_otel(), "/trace-id").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(trace_id.len(), 32);
+        assert_ne!(trace_id, trace::TraceId::INVALID.to_string());
+        let spans = collector.spans();
+        assert_eq!(spans[0].span_context.trace_id().to_string(), trace_id);
+
+        let (_, traceparent) = get_body(app.clone().with_otel(), "/traceparent").await;
+        assert!(traceparent.starts_with("00-"));
+
+        let (status, _) = get_body(app, "/trace-id").await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
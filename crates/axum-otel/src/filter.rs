@@ -0,0 +1,256 @@
+use crate::RequestInfo;
+use axum::http::{header, Method};
+use std::{fmt, sync::Arc};
+use tracing::Level;
+use tracing_otel_extra::extract::fields;
+
+type Predicate = Arc<dyn Fn(&RequestInfo<'_>) -> bool + Send + Sync>;
+
+/// What happens to the request span of a request matched by a [`RequestFilter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Do not create a span, and do not emit the response and failure events.
+    #[default]
+    Skip,
+    /// Create the span at the given level instead.
+    Level(Level),
+}
+
+#[derive(Clone, Default)]
+struct FilterRules {
+    paths: Vec<String>,
+    methods: Vec<Method>,
+    user_agents: Vec<String>,
+    predicates: Vec<Predicate>,
+    action: FilterAction,
+}
+
+/// Selects requests, such as health checks and probes, for which tracing is skipped
+/// or downgraded.
+///
+/// A request is matched when any of the configured rules matches:
+///
+/// - [`RequestFilter::path`]: a glob matched against both the [`MatchedPath`] and the
+///   raw request path. `*` matches within a single path segment and `**` matches
+///   across segments.
+/// - [`RequestFilter::method`]: the request method, e.g. `OPTIONS` for CORS preflights.
+/// - [`RequestFilter::user_agent`]: a glob matched against the `User-Agent` header,
+///   where `*` matches any characters.
+/// - [`RequestFilter::predicate`]: a custom closure.
+///
+/// [`MatchedPath`]: axum::extract::MatchedPath
+///
+/// # Example
+///
+/// ```rust
+/// use axum::http::Method;
+/// use axum_otel::{AxumOtelSpanCreator, FilterAction, Level, RequestFilter};
+///
+/// let filter = RequestFilter::new()
+///     .path("/health")
+///     .path("/ready")
+///     .path("/internal/**")
+///     .method(Method::OPTIONS)
+///     .user_agent("kube-probe/*")
+///     .predicate(|request| request.headers().contains_key("x-synthetic"))
+///     .action(FilterAction::Level(Level::TRACE));
+///
+/// let make_span = AxumOtelSpanCreator::new().level(Level::INFO).filter(filter);
+/// ```
+#[derive(Clone, Default)]
+pub struct RequestFilter {
+    rules: Arc<FilterRules>,
+}
+
+impl RequestFilter {
+    /// Create a new `RequestFilter` that matches no requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match requests whose route or path matches the glob pattern.
+    pub fn path(mut self, pattern: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.rules).paths.push(pattern.into());
+        self
+    }
+
+    /// Match requests with the given method.
+    pub fn method(mut self, method: Method) -> Self {
+        Arc::make_mut(&mut self.rules).methods.push(method);
+        self
+    }
+
+    /// Match requests whose `User-Agent` header matches the glob pattern.
+    pub fn user_agent(mut self, pattern: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.rules)
+            .user_agents
+            .push(pattern.into());
+        self
+    }
+
+    /// Match requests for which the predicate returns `true`.
+    pub fn predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&RequestInfo<'_>) -> bool + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.rules)
+            .predicates
+            .push(Arc::new(predicate));
+        self
+    }
+
+    /// Set the [`FilterAction`] applied to matched requests.
+    ///
+    /// Defaults to [`FilterAction::Skip`].
+    pub fn action(mut self, action: FilterAction) -> Self {
+        Arc::make_mut(&mut self.rules).action = action;
+        self
+    }
+
+    /// Returns the [`FilterAction`] for the request, or `None` if it is not matched.
+    pub fn evaluate(&self, request: &RequestInfo<'_>) -> Option<FilterAction> {
+        self.matches(request).then_some(self.rules.action)
+    }
+
+    fn matches(&self, request: &RequestInfo<'_>) -> bool {
+        let rules = &self.rules;
+        if rules.methods.contains(request.method()) {
+            return true;
+        }
+        let route = request.matched_path();
+        let path = request.path();
+        if rules.paths.iter().any(|pattern| {
+            route.is_some_and(|route| glob_match(pattern, route, Some(b'/')))
+                || glob_match(pattern, path, Some(b'/'))
+        }) {
+            return true;
+        }
+        if let Some(user_agent) =
+            fields::extract_field_from_headers(request.headers(), header::USER_AGENT)
+        {
+            if rules
+                .user_agents
+                .iter()
+                .any(|pattern| glob_match(pattern, user_agent, None))
+            {
+                return true;
+            }
+        }
+        rules.predicates.iter().any(|predicate| predicate(request))
+    }
+}
+
+impl fmt::Debug for RequestFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestFilter")
+            .field("paths", &self.rules.paths)
+            .field("methods", &self.rules.methods)
+            .field("user_agents", &self.rules.user_agents)
+            .field("predicates", &self.rules.predicates.len())
+            .field("action", &self.rules.action)
+            .finish()
+    }
+}
+
+/// Match `text` against a glob `pattern`.
+///
+/// `*` matches any characters except `separator`, and `**` matches any characters.
+///
+/// The pattern is matched byte by byte, resuming after the last star on a mismatch, so
+/// matching takes at most `pattern.len() * text.len()` steps.
+fn glob_match(pattern: &str, text: &str, separator: Option<u8>) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // The pattern index after the last `*` or `**` and the text index it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    let mut double_star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if pattern.get(p) == Some(&b'*') {
+            if pattern.get(p + 1) == Some(&b'*') {
+                p += 2;
+                double_star = Some((p, t));
+                star = None;
+            } else if separator.is_none() {
+                p += 1;
+                double_star = Some((p, t));
+                star = None;
+            } else {
+                p += 1;
+                star = Some((p, t));
+            }
+            continue;
+        }
+        if pattern.get(p) == Some(&text[t]) {
+            p += 1;
+            t += 1;
+            continue;
+        }
+
+        // Let the last `*` match one more byte, unless that byte is a separator
+        if let Some((star_p, star_t)) = star {
+            if Some(text[star_t]) != separator {
+                star = Some((star_p, star_t + 1));
+                (p, t) = (star_p, star_t + 1);
+                continue;
+            }
+        }
+        // Otherwise let the last `**` match one more byte and retry what follows it
+        if let Some((star_p, star_t)) = double_star {
+            double_star = Some((star_p, star_t + 1));
+            star = None;
+            (p, t) = (star_p, star_t + 1);
+            continue;
+        }
+        return false;
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    #[test]
+    fn test_glob_match_paths() {
+        assert!(glob_match("/health", "/health", Some(b'/')));
+        assert!(!glob_match("/health", "/healthz", Some(b'/')));
+        assert!(glob_match("/users/*", "/users/42", Some(b'/')));
+        assert!(!glob_match("/users/*", "/users/42/posts", Some(b'/')));
+        assert!(glob_match("/internal/**", "/internal/a/b", Some(b'/')));
+        assert!(glob_match("kube-probe/*", "kube-probe/1.27", None));
+        assert!(glob_match("/**/a*b", "/a/x/ab", Some(b'/')));
+        assert!(!glob_match("/**/a*b", "/a/x/a/b", Some(b'/')));
+
+        // Stars that cannot match are not retried exponentially
+        let pattern = "*a".repeat(32);
+        assert!(!glob_match(&pattern, &format!("{}b", "a".repeat(64)), None));
+    }
+
+    #[test]
+    fn test_request_filter() {
+        let filter = RequestFilter::new()
+            .path("/health")
+            .method(Method::OPTIONS)
+            .user_agent("kube-probe/*");
+
+        let request = Request::get("/health").body(()).unwrap();
+        assert_eq!(
+            filter.evaluate(&RequestInfo::new(&request)),
+            Some(FilterAction::Skip)
+        );
+
+        let request = Request::options("/users").body(()).unwrap();
+        assert!(filter.evaluate(&RequestInfo::new(&request)).is_some());
+
+        let request = Request::get("/users")
+            .header(header::USER_AGENT, "kube-probe/1.27")
+            .body(())
+            .unwrap();
+        assert!(filter.evaluate(&RequestInfo::new(&request)).is_some());
+
+        let request = Request::get("/users").body(()).unwrap();
+        assert!(filter.evaluate(&RequestInfo::new(&request)).is_none());
+    }
+}
@@ -0,0 +1,271 @@
+use axum::http::{header, HeaderMap, HeaderName};
+use hmac::{Hmac, Mac};
+use opentelemetry::{Array, StringValue, Value};
+use sha2::Sha256;
+use std::{
+    fmt::{self, Write},
+    sync::Arc,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::extract::fields;
+
+/// The attribute key prefix for captured request headers.
+pub(crate) const REQUEST_HEADER_PREFIX: &str = "http.request.header";
+
+/// The attribute key prefix for captured response headers.
+pub(crate) const RESPONSE_HEADER_PREFIX: &str = "http.response.header";
+
+/// The value recorded in place of a redacted header.
+const REDACTED: &str = "[REDACTED]";
+
+/// Headers that are always treated as sensitive.
+const DEFAULT_SENSITIVE_HEADERS: [HeaderName; 5] = [
+    header::AUTHORIZATION,
+    header::PROXY_AUTHORIZATION,
+    header::COOKIE,
+    header::SET_COOKIE,
+    HeaderName::from_static("x-api-key"),
+];
+
+/// How the values of sensitive headers are recorded.
+#[derive(Clone, Default)]
+pub struct Redaction(RedactionMode);
+
+#[derive(Clone, Default)]
+enum RedactionMode {
+    #[default]
+    Redact,
+    HmacSha256(Hmac<Sha256>),
+}
+
+impl Redaction {
+    /// Replace the value with `[REDACTED]`.
+    ///
+    /// This is the default.
+    pub fn redact() -> Self {
+        Self(RedactionMode::Redact)
+    }
+
+    /// Replace the value with `hmac-sha256:<hex>`, its HMAC-SHA256 keyed with `key`, so
+    /// equal values can still be correlated across requests and releases.
+    ///
+    /// The key must be a secret of at least 32 random bytes that is kept out of the
+    /// exported telemetry, otherwise low-entropy values such as passwords or short
+    /// tokens can be recovered by hashing candidates. Rotating the key changes all
+    /// hashes.
+    pub fn hmac_sha256(key: impl AsRef<[u8]>) -> Self {
+        let mac = Hmac::new_from_slice(key.as_ref()).expect("HMAC accepts keys of any length");
+        Self(RedactionMode::HmacSha256(mac))
+    }
+
+    fn apply(&self, value: &[u8]) -> String {
+        match &self.0 {
+            RedactionMode::Redact => REDACTED.to_owned(),
+            RedactionMode::HmacSha256(mac) => {
+                let digest = mac.clone().chain_update(value).finalize().into_bytes();
+                let mut hash = String::from("hmac-sha256:");
+                for byte in digest {
+                    write!(hash, "{:02x}", byte).expect("writing to a String can't fail");
+                }
+                hash
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the key
+        match self.0 {
+            RedactionMode::Redact => f.write_str("Redact"),
+            RedactionMode::HmacSha256(_) => f.write_str("HmacSha256(..)"),
+        }
+    }
+}
+
+/// An allowlist of headers to record as span attributes.
+///
+/// Each captured header is recorded as `http.request.header.<name>` or
+/// `http.response.header.<name>`, a string array with one item per header value in the
+/// order they were received. Values that are not valid UTF-8 are skipped. Header names
+/// are case-insensitive. Headers not in the allowlist are never recorded.
+/// Sensitive headers such as `authorization`, `proxy-authorization`, `cookie`,
+/// `set-cookie` and `x-api-key` are recorded according to the [`Redaction`] policy
+/// and never verbatim.
+///
+/// The attributes are set on the OpenTelemetry span directly, so they are exported
+/// but not included in formatted log output.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::http::{header, HeaderName};
+/// use axum_otel::{AxumOtelOnResponse, AxumOtelSpanCreator, HeaderCapture, Redaction};
+///
+/// let make_span = AxumOtelSpanCreator::new().request_headers(
+///     HeaderCapture::new([
+///         HeaderName::from_static("x-tenant-id"),
+///         header::CONTENT_TYPE,
+///         header::AUTHORIZATION,
+///     ])
+///     .redaction(Redaction::hmac_sha256(b"a secret key of at least 32 bytes")),
+/// );
+/// let on_response = AxumOtelOnResponse::new()
+///     .response_headers(HeaderCapture::new([HeaderName::from_static("x-cache")]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeaderCapture {
+    headers: Arc<[HeaderName]>,
+    sensitive: Arc<[HeaderName]>,
+    redaction: Redaction,
+}
+
+impl HeaderCapture {
+    /// Create a new `HeaderCapture` recording the given headers.
+    pub fn new(headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        Self {
+            headers: headers.into_iter().collect(),
+            sensitive: DEFAULT_SENSITIVE_HEADERS.into(),
+            redaction: Redaction::default(),
+        }
+    }
+
+    /// Mark additional headers as sensitive.
+    pub fn sensitive(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.sensitive = self.sensitive.iter().cloned().chain(headers).collect();
+        self
+    }
+
+    /// Set the [`Redaction`] applied to sensitive headers.
+    ///
+    /// Defaults to [`Redaction::redact`].
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Record the captured headers on the span, using the given attribute key prefix.
+    pub(crate) fn record(&self, prefix: &str, headers: &HeaderMap, span: &tracing::Span) {
+        for name in self.headers.iter() {
+            let sensitive = self.sensitive.contains(name);
+            let values: Vec<StringValue> =
+                fields::extract_field_values_from_headers(headers, name.clone())
+                    .map(|value| {
+                        if sensitive {
+                            self.redaction.apply(value.as_bytes())
+                        } else {
+                            value.to_owned()
+                        }
+                    })
+                    .map(StringValue::from)
+                    .collect();
+            if values.is_empty() {
+                continue;
+            }
+            span.set_attribute(
+                format!("{}.{}", prefix, name),
+                Value::Array(Array::String(values)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{attribute, SpanCollector};
+
+    fn capture(capture: &HeaderCapture, headers: &[(&str, &str)]) -> SpanCollector {
+        let collector = SpanCollector::install();
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(HeaderName::try_from(*name).unwrap(), value.parse().unwrap());
+        }
+        let span = tracing::info_span!("request");
+        capture.record(REQUEST_HEADER_PREFIX, &map, &span);
+        drop(span);
+        collector
+    }
+
+    fn header(collector: &SpanCollector, name: &str) -> Option<Vec<String>> {
+        let span = &collector.spans()[0];
+        match attribute(span, &format!("{}.{}", REQUEST_HEADER_PREFIX, name))? {
+            Value::Array(Array::String(values)) => {
+                Some(values.into_iter().map(String::from).collect())
+            }
+            value => panic!("unexpected header value {:?}", value),
+        }
+    }
+
+    #[test]
+    fn test_only_allowlisted_headers_are_captured() {
+        let collector = capture(
+            &HeaderCapture::new([header::CONTENT_TYPE]),
+            &[("content-type", "application/json"), ("x-other", "1")],
+        );
+        assert_eq!(
+            header(&collector, "content-type").unwrap(),
+            ["application/json"]
+        );
+        assert_eq!(header(&collector, "x-other"), None);
+        assert_eq!(header(&collector, "accept"), None);
+    }
+
+    #[test]
+    fn test_sensitive_headers_are_redacted_by_default() {
+        let collector = capture(
+            &HeaderCapture::new([header::AUTHORIZATION, header::COOKIE, header::SET_COOKIE]),
+            &[
+                ("authorization", "Basic dXNlcjpwYXNz"),
+                ("cookie", "session=abc"),
+                ("set-cookie", "session=def"),
+            ],
+        );
+        for name in ["authorization", "cookie", "set-cookie"] {
+            assert_eq!(header(&collector, name).unwrap(), [REDACTED]);
+        }
+    }
+
+    #[test]
+    fn test_header_names_are_case_insensitive() {
+        let collector = capture(
+            &HeaderCapture::new([HeaderName::try_from("X-Tenant-Id").unwrap()])
+                .sensitive([HeaderName::try_from("X-Tenant-Id").unwrap()]),
+            &[("X-TENANT-ID", "acme")],
+        );
+        assert_eq!(header(&collector, "x-tenant-id").unwrap(), [REDACTED]);
+    }
+
+    #[test]
+    fn test_all_values_of_multi_valued_headers_are_captured() {
+        let collector = capture(
+            &HeaderCapture::new([header::ACCEPT, header::COOKIE]),
+            &[
+                ("accept", "text/html"),
+                ("accept", "application/json"),
+                ("cookie", "a=1"),
+                ("cookie", "b=2"),
+            ],
+        );
+        assert_eq!(
+            header(&collector, "accept").unwrap(),
+            ["text/html", "application/json"]
+        );
+        assert_eq!(header(&collector, "cookie").unwrap(), [REDACTED, REDACTED]);
+    }
+
+    #[test]
+    fn test_hmac_redaction() {
+        let redaction = Redaction::hmac_sha256("key");
+        // The well-known HMAC_SHA256("key", "The quick brown fox ...") example
+        assert_eq!(
+            redaction.apply(b"The quick brown fox jumps over the lazy dog"),
+            "hmac-sha256:f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_ne!(
+            Redaction::hmac_sha256("other key").apply(b"secret"),
+            redaction.apply(b"secret")
+        );
+        assert_eq!(format!("{:?}", redaction), "HmacSha256(..)");
+    }
+}
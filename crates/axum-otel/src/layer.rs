@@ -0,0 +1,667 @@
+use crate::{
+    access_log::{AccessLog, AccessLogLayer},
+    body_capture::{BodyCapture, BodyCaptureLayer},
+    cancel::{CancellationLayer, DetectCancellation},
+    extract::{InsertOtelContext, OtelContextLayer},
+    metrics::{HttpMetrics, HttpMetricsLayer},
+    on_body::{ResponseBodyLayer, TrackResponseBody},
+    panic::{CatchPanic, CatchPanicLayer},
+    trace_response::{TraceContextResponse, TraceContextResponseLayer},
+    AxumOtelOnBodyChunk, AxumOtelOnEos, AxumOtelOnFailure, AxumOtelOnResponse, AxumOtelSpanCreator,
+    HttpSemConv, RequestFilter, RequestInfo, ResponseInfo, SlowRequests, SpanAttributes,
+    StatusLevels, StatusPolicy,
+};
+use axum::http::{HeaderName, Request};
+use std::{fmt, sync::Arc};
+use tower::{
+    util::{option_layer, Either},
+    Layer,
+};
+use tower_http::{
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    request_id::{
+        MakeRequestId, MakeRequestUuid, PropagateRequestId, PropagateRequestIdLayer, RequestId,
+        SetRequestId, SetRequestIdLayer,
+    },
+    trace::{DefaultOnBodyChunk, DefaultOnRequest, Trace, TraceLayer},
+};
+use tracing::Level;
+use tracing_otel_extra::extract::fields::{TrustedProxies, X_REQUEST_ID};
+
+/// The [`Trace`] middleware configured with the axum-otel components.
+pub type AxumOtelTrace<S> = Trace<
+    S,
+    SharedClassifier<ServerErrorsAsFailures>,
+    AxumOtelSpanCreator,
+    DefaultOnRequest,
+    AxumOtelOnResponse,
+    DefaultOnBodyChunk,
+    AxumOtelOnEos,
+    AxumOtelOnFailure,
+>;
+
+/// The panic capture applied inside the trace layer.
+type Panic<S> = Either<CatchPanic<S>, S>;
+
+/// The request id propagation applied inside the trace layer.
+type Propagate<S> = Either<PropagateRequestId<S>, S>;
+
+/// The body capture applied inside the trace layer.
+type Capture<S> = Either<BodyCapture<S>, S>;
+
+/// The trace context response headers applied inside the trace layer.
+type TraceResponse<S> = Either<TraceContextResponse<S>, S>;
+
+/// The metrics recording applied inside the trace layer.
+type Metrics<S> = Either<HttpMetrics<S>, S>;
+
+/// The cancellation detection applied inside the trace layer.
+type Cancel<S> = Either<DetectCancellation<S>, S>;
+
+/// The access log applied inside the trace layer.
+type Access<S> = Either<AccessLog<S>, S>;
+
+/// Layer settings applied on top of a component when the layer is applied, so they are
+/// kept when the component is replaced.
+struct Settings<T>(Vec<Arc<dyn Fn(T) -> T + Send + Sync>>);
+
+impl<T> Settings<T> {
+    fn push<F>(&mut self, setting: F)
+    where
+        F: Fn(T) -> T + Send + Sync + 'static,
+    {
+        self.0.push(Arc::new(setting));
+    }
+
+    fn apply(&self, component: T) -> T {
+        self.0
+            .iter()
+            .fold(component, |component, setting| setting(component))
+    }
+}
+
+impl<T> Clone for Settings<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for Settings<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T> fmt::Debug for Settings<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Settings").field(&self.0.len()).finish()
+    }
+}
+
+/// The service produced by [`AxumOtelLayer`].
+pub type AxumOtelService<S> = SetRequestId<
+    AxumOtelTrace<
+        TrackResponseBody<
+            Access<Cancel<Metrics<TraceResponse<Capture<Propagate<Panic<InsertOtelContext<S>>>>>>>>,
+        >,
+    >,
+    AxumOtelMakeRequestId,
+>;
+
+/// A [`Layer`] that wires up the complete axum-otel middleware stack.
+///
+/// The layer applies, from outermost to innermost:
+///
+/// 1. [`SetRequestIdLayer`] - sets a request id on requests without one
+/// 2. [`TraceLayer`] - configured with [`AxumOtelSpanCreator`], [`AxumOtelOnResponse`],
+///    [`AxumOtelOnEos`] and [`AxumOtelOnFailure`]
+/// 3. [`ResponseBodyLayer`] - records the response body with [`AxumOtelOnBodyChunk`]
+/// 4. [`AccessLogLayer`] - emits one access log event per request, when enabled
+/// 5. [`CancellationLayer`] - records requests cancelled by the client, when enabled
+/// 6. [`HttpMetricsLayer`] - records the HTTP server metrics, when enabled
+/// 7. [`TraceContextResponseLayer`] - writes the trace context into the response
+///    headers, when enabled
+/// 8. [`BodyCaptureLayer`] - records the request and response bodies, when enabled
+/// 9. [`PropagateRequestIdLayer`] - copies the request id to the response
+/// 10. [`CatchPanicLayer`] - records panics and responds with a `500`, when enabled
+/// 11. [`OtelContextLayer`] - stores the [`OtelContext`] of the request span in the
+///     request extensions, and the matched route in the response extensions
+///
+/// [`OtelContext`]: crate::OtelContext
+///
+/// Setting the request id before the trace layer runs makes sure the request id is
+/// always recorded on the request span.
+///
+/// Settings such as [`AxumOtelLayer::semconv`] or [`AxumOtelLayer::trusted_proxies`]
+/// are applied to the components when the layer is applied, on top of the components
+/// set with [`AxumOtelLayer::make_span_with`], [`AxumOtelLayer::on_response`] and
+/// [`AxumOtelLayer::on_failure`], whatever the order of the calls.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelLayer, Level};
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .layer(
+///         AxumOtelLayer::new()
+///             .span_level(Level::INFO)
+///             .response_level(Level::INFO),
+///     );
+/// ```
+#[derive(Clone, Debug)]
+pub struct AxumOtelLayer {
+    make_span: AxumOtelSpanCreator,
+    on_response: AxumOtelOnResponse,
+    on_body_chunk: AxumOtelOnBodyChunk,
+    on_eos: AxumOtelOnEos,
+    on_failure: AxumOtelOnFailure,
+    make_span_settings: Settings<AxumOtelSpanCreator>,
+    on_response_settings: Settings<AxumOtelOnResponse>,
+    on_failure_settings: Settings<AxumOtelOnFailure>,
+    request_id_header: HeaderName,
+    generate_request_id: bool,
+    propagate_request_id: bool,
+    metrics: Option<HttpMetricsLayer>,
+    trace_context_response: Option<TraceContextResponseLayer>,
+    body_capture: Option<BodyCaptureLayer>,
+    catch_panic: Option<CatchPanicLayer>,
+    cancellation: Option<CancellationLayer>,
+    access_log: Option<AccessLogLayer>,
+    trusted_proxies: TrustedProxies,
+    unmatched_route: Option<Arc<str>>,
+}
+
+impl AxumOtelLayer {
+    /// Create a new `AxumOtelLayer` with the default components.
+    pub fn new() -> Self {
+        Self {
+            make_span: AxumOtelSpanCreator::new(),
+            on_response: AxumOtelOnResponse::new(),
+            on_body_chunk: AxumOtelOnBodyChunk::new(),
+            on_eos: AxumOtelOnEos::new(),
+            on_failure: AxumOtelOnFailure::new(),
+            make_span_settings: Settings::default(),
+            on_response_settings: Settings::default(),
+            on_failure_settings: Settings::default(),
+            request_id_header: X_REQUEST_ID,
+            generate_request_id: true,
+            propagate_request_id: true,
+            metrics: None,
+            trace_context_response: None,
+            body_capture: None,
+            catch_panic: None,
+            cancellation: Some(CancellationLayer::new()),
+            access_log: None,
+            trusted_proxies: TrustedProxies::default(),
+            unmatched_route: None,
+        }
+    }
+
+    /// Set the [`Level`] used for the request span.
+    ///
+    /// Defaults to [`Level::TRACE`].
+    pub fn span_level(mut self, level: Level) -> Self {
+        self.make_span_settings
+            .push(move |make_span| make_span.level(level));
+        self
+    }
+
+    /// Set the [`Level`] used for the response event.
+    ///
+    /// Defaults to [`Level::DEBUG`].
+    pub fn response_level(mut self, level: Level) -> Self {
+        self.on_response_settings
+            .push(move |on_response| on_response.level(level));
+        self
+    }
+
+    /// Set the [`Level`] used for the failure event.
+    ///
+    /// Defaults to [`Level::ERROR`].
+    pub fn failure_level(mut self, level: Level) -> Self {
+        self.on_failure_settings
+            .push(move |on_failure| on_failure.level(level));
+        self
+    }
+
+    /// Set the [`HttpSemConv`] recorded by the span creator and the response handler.
+    ///
+    /// Defaults to [`HttpSemConv::Legacy`].
+    pub fn semconv(mut self, semconv: HttpSemConv) -> Self {
+        self.make_span_settings
+            .push(move |make_span| make_span.semconv(semconv));
+        self.on_response_settings
+            .push(move |on_response| on_response.semconv(semconv));
+        self
+    }
+
+    /// Set the [`StatusPolicy`] used by the response and failure handlers to set the
+    /// span status.
+    ///
+    /// Defaults to "OK" for successful responses and "ERROR" for `5xx` responses.
+    pub fn status_policy(mut self, status_policy: StatusPolicy) -> Self {
+        let on_response_policy = status_policy.clone();
+        self.on_response_settings
+            .push(move |on_response| on_response.status_policy(on_response_policy.clone()));
+        self.on_failure_settings
+            .push(move |on_failure| on_failure.status_policy(status_policy.clone()));
+        self
+    }
+
+    /// Set the [`StatusLevels`] used by the response and failure handlers to pick the
+    /// event level from the response status.
+    ///
+    /// This replaces the levels set with [`AxumOtelLayer::response_level`] and
+    /// [`AxumOtelLayer::failure_level`].
+    pub fn status_levels(mut self, levels: StatusLevels) -> Self {
+        let on_response_levels = levels.clone();
+        self.on_response_settings
+            .push(move |on_response| on_response.status_levels(on_response_levels.clone()));
+        self.on_failure_settings
+            .push(move |on_failure| on_failure.status_levels(levels.clone()));
+        self
+    }
+
+    /// Skip or downgrade tracing for requests matched by the [`RequestFilter`].
+    pub fn filter(mut self, filter: RequestFilter) -> Self {
+        self.make_span_settings
+            .push(move |make_span| make_span.filter(filter.clone()));
+        self
+    }
+
+    /// Name request spans with the given closure, see [`AxumOtelSpanCreator::span_name`].
+    pub fn span_name<F>(mut self, span_name: F) -> Self
+    where
+        F: Fn(&RequestInfo<'_>) -> String + Send + Sync + 'static,
+    {
+        let span_name = Arc::new(span_name);
+        self.make_span_settings.push(move |make_span| {
+            let span_name = span_name.clone();
+            make_span.span_name(move |request| span_name(request))
+        });
+        self
+    }
+
+    /// Record extra attributes on the request span when it is created, see
+    /// [`AxumOtelSpanCreator::enrich`].
+    pub fn enrich_request<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&RequestInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync + 'static,
+    {
+        let callback = Arc::new(callback);
+        self.make_span_settings.push(move |make_span| {
+            let callback = callback.clone();
+            make_span.enrich(move |request, attributes| callback(request, attributes))
+        });
+        self
+    }
+
+    /// Record extra attributes on the request span when the response is ready, see
+    /// [`AxumOtelOnResponse::enrich`].
+    pub fn enrich_response<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ResponseInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync + 'static,
+    {
+        let callback = Arc::new(callback);
+        self.on_response_settings.push(move |on_response| {
+            let callback = callback.clone();
+            on_response.enrich(move |response, attributes| callback(response, attributes))
+        });
+        self
+    }
+
+    /// Report requests slower than the [`SlowRequests`] thresholds, see
+    /// [`AxumOtelOnResponse::slow_requests`].
+    pub fn slow_requests(mut self, slow_requests: SlowRequests) -> Self {
+        self.on_response_settings
+            .push(move |on_response| on_response.slow_requests(slow_requests.clone()));
+        self
+    }
+
+    /// Set the [`TrustedProxies`] whose forwarding headers are used to find the client ip.
+    ///
+    /// The proxies are used by the span creator and the access log.
+    pub fn trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        let make_span_proxies = trusted_proxies.clone();
+        self.make_span_settings
+            .push(move |make_span| make_span.trusted_proxies(make_span_proxies.clone()));
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Replace the [`AxumOtelSpanCreator`] used to create the request span.
+    ///
+    /// The settings of this layer, such as [`AxumOtelLayer::semconv`], are still applied
+    /// on top of it.
+    pub fn make_span_with(mut self, make_span: AxumOtelSpanCreator) -> Self {
+        self.make_span = make_span;
+        self
+    }
+
+    /// Replace the [`AxumOtelOnResponse`] used to record the response.
+    ///
+    /// The settings of this layer, such as [`AxumOtelLayer::response_level`], are still
+    /// applied on top of it.
+    pub fn on_response(mut self, on_response: AxumOtelOnResponse) -> Self {
+        self.on_response = on_response;
+        self
+    }
+
+    /// Replace the [`AxumOtelOnBodyChunk`] used to record the response body.
+    pub fn on_body_chunk(mut self, on_body_chunk: AxumOtelOnBodyChunk) -> Self {
+        self.on_body_chunk = on_body_chunk;
+        self
+    }
+
+    /// Replace the [`AxumOtelOnEos`] used to record the end of response streams.
+    pub fn on_eos(mut self, on_eos: AxumOtelOnEos) -> Self {
+        self.on_eos = on_eos;
+        self
+    }
+
+    /// Replace the [`AxumOtelOnFailure`] used to record failures.
+    ///
+    /// The settings of this layer, such as [`AxumOtelLayer::failure_level`], are still
+    /// applied on top of it.
+    pub fn on_failure(mut self, on_failure: AxumOtelOnFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+
+    /// Set the header used to read, generate and propagate the request id.
+    ///
+    /// Defaults to `x-request-id`.
+    pub fn request_id_header(mut self, header_name: HeaderName) -> Self {
+        self.request_id_header = header_name;
+        self
+    }
+
+    /// Set whether a UUID request id is generated for requests without one.
+    ///
+    /// Defaults to `true`.
+    pub fn generate_request_id(mut self, generate: bool) -> Self {
+        self.generate_request_id = generate;
+        self
+    }
+
+    /// Set whether the request id is copied to the response headers.
+    ///
+    /// Defaults to `true`.
+    pub fn propagate_request_id(mut self, propagate: bool) -> Self {
+        self.propagate_request_id = propagate;
+        self
+    }
+
+    /// Set whether the HTTP server metrics are recorded.
+    ///
+    /// The instruments are created on the global meter provider when this is
+    /// called, see [`HttpMetricsLayer`] for details.
+    ///
+    /// Defaults to `false`.
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled.then(HttpMetricsLayer::new);
+        self
+    }
+
+    /// Record the HTTP server metrics with the given [`HttpMetricsLayer`].
+    pub fn http_metrics(mut self, layer: HttpMetricsLayer) -> Self {
+        self.metrics = Some(layer);
+        self
+    }
+
+    /// Record requests that matched no route with the given `http.route`, on the span
+    /// and on the metrics recorded by this layer.
+    ///
+    /// By default `http.route` is left out for these requests.
+    pub fn unmatched_route(mut self, route: impl Into<Arc<str>>) -> Self {
+        self.unmatched_route = Some(route.into());
+        self
+    }
+
+    /// Write the trace context into the response headers with the given
+    /// [`TraceContextResponseLayer`].
+    ///
+    /// By default no trace context is written into the response.
+    pub fn trace_context_response(mut self, layer: TraceContextResponseLayer) -> Self {
+        self.trace_context_response = Some(layer);
+        self
+    }
+
+    /// Record the request and response bodies with the given [`BodyCaptureLayer`].
+    ///
+    /// By default no bodies are recorded.
+    pub fn body_capture(mut self, layer: BodyCaptureLayer) -> Self {
+        self.body_capture = Some(layer);
+        self
+    }
+
+    /// Set whether panics in handlers are recorded on the request span and turned into
+    /// `500 Internal Server Error` responses.
+    ///
+    /// The panic location is only recorded once [`install_panic_hook`] was called, see
+    /// [`CatchPanicLayer`] for details.
+    ///
+    /// [`install_panic_hook`]: crate::install_panic_hook
+    ///
+    /// Defaults to `false`.
+    pub fn catch_panic(mut self, enabled: bool) -> Self {
+        self.catch_panic = enabled.then(CatchPanicLayer::new);
+        self
+    }
+
+    /// Set whether requests cancelled before the response was ready, for example
+    /// because the client disconnected, are recorded on the request span.
+    ///
+    /// Defaults to `true`.
+    pub fn detect_cancellation(mut self, enabled: bool) -> Self {
+        self.cancellation = enabled.then(CancellationLayer::new);
+        self
+    }
+
+    /// Record cancelled requests with the given [`CancellationLayer`].
+    pub fn cancellation(mut self, layer: CancellationLayer) -> Self {
+        self.cancellation = Some(layer);
+        self
+    }
+
+    /// Emit one access log event per request with the given [`AccessLogLayer`].
+    ///
+    /// The layer uses the trusted proxies set with [`AxumOtelLayer::trusted_proxies`].
+    ///
+    /// By default no access log is emitted.
+    pub fn access_log(mut self, layer: AccessLogLayer) -> Self {
+        self.access_log = Some(layer);
+        self
+    }
+}
+
+impl Default for AxumOtelLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for AxumOtelLayer {
+    type Service = AxumOtelService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let propagate = option_layer(
+            self.propagate_request_id
+                .then(|| PropagateRequestIdLayer::new(self.request_id_header.clone())),
+        );
+        let catch_panic = option_layer(self.catch_panic);
+        let capture = option_layer(self.body_capture.clone());
+        let trace_response = option_layer(self.trace_context_response.clone());
+        let metrics = option_layer(
+            self.metrics
+                .clone()
+                .map(|layer| match &self.unmatched_route {
+                    Some(route) => layer.unmatched_route(route.clone()),
+                    None => layer,
+                }),
+        );
+        let cancellation = option_layer(self.cancellation);
+        let access_log = option_layer(
+            self.access_log
+                .clone()
+                .map(|layer| layer.trusted_proxies(self.trusted_proxies.clone())),
+        );
+        let make_span = self.make_span_settings.apply(self.make_span.clone());
+        let make_span = match &self.unmatched_route {
+            Some(route) => make_span.unmatched_route(route.clone()),
+            None => make_span,
+        };
+        let trace = TraceLayer::new_for_http()
+            .make_span_with(make_span)
+            .on_response(self.on_response_settings.apply(self.on_response.clone()))
+            .on_eos(self.on_eos)
+            .on_failure(self.on_failure_settings.apply(self.on_failure.clone()));
+        let set_request_id = SetRequestIdLayer::new(
+            self.request_id_header.clone(),
+            AxumOtelMakeRequestId {
+                generate: self.generate_request_id,
+            },
+        );
+
+        let inner = catch_panic.layer(OtelContextLayer::new().layer(inner));
+        let inner = capture.layer(propagate.layer(inner));
+        let inner = metrics.layer(trace_response.layer(inner));
+        let inner = access_log.layer(cancellation.layer(inner));
+        let inner = ResponseBodyLayer::new(self.on_body_chunk.clone()).layer(inner);
+        set_request_id.layer(trace.layer(inner))
+    }
+}
+
+/// A [`MakeRequestId`] that generates UUID request ids when enabled.
+///
+/// Incoming request ids are always kept, so disabling generation only affects
+/// requests that arrive without one.
+#[derive(Clone, Copy, Debug)]
+pub struct AxumOtelMakeRequestId {
+    generate: bool,
+}
+
+impl MakeRequestId for AxumOtelMakeRequestId {
+    fn make_request_id<B>(&mut self, request: &Request<B>) -> Option<RequestId> {
+        if self.generate {
+            MakeRequestUuid.make_request_id(request)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelRouterExt, SpanId,
+    };
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{Response, StatusCode},
+        routing::get,
+        Router,
+    };
+    use opentelemetry::trace::SpanKind;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/users/{id}",
+                get(|SpanId(span_id): SpanId| async move { span_id.to_string() }),
+            )
+            .with_otel()
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_with_otel_records_the_request_span() {
+        let collector = SpanCollector::install();
+        let request = Request::get("/users/42")
+            .header("x-request-id", "abc")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-request-id"], "abc");
+        let handler_span_id = body(response).await;
+
+        let span = &collector.spans()[0];
+        assert_eq!(span.name, "GET /users/{id}");
+        assert_eq!(span.span_kind, SpanKind::Server);
+        assert_eq!(attribute(span, "http.route"), Some("/users/{id}".into()));
+        assert_eq!(attribute(span, "http.status_code"), Some(200.into()));
+        assert_eq!(attribute(span, "request_id"), Some("abc".into()));
+        assert_eq!(
+            attribute(span, "trace_id"),
+            Some(span.span_context.trace_id().to_string().into())
+        );
+        // The handler sees the request span through the OtelContext extension
+        assert_eq!(handler_span_id, span.span_context.span_id().to_string());
+    }
+
+    #[tokio::test]
+    async fn test_with_otel_generates_a_request_id() {
+        let collector = SpanCollector::install();
+        let request = Request::get("/users/42").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        let request_id = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(request_id.len(), 36);
+        body(response).await;
+
+        let span = &collector.spans()[0];
+        assert_eq!(attribute(span, "request_id"), Some(request_id.into()));
+    }
+
+    #[tokio::test]
+    async fn test_settings_apply_to_replaced_components() {
+        let collector = SpanCollector::install();
+        let layer = AxumOtelLayer::new()
+            .semconv(HttpSemConv::Stable)
+            .trusted_proxies(TrustedProxies::parse(["10.0.0.0/8"]).unwrap())
+            .make_span_with(AxumOtelSpanCreator::new().span_name(|_| "custom".to_owned()))
+            .on_response(AxumOtelOnResponse::new());
+        let app = Router::new().route("/", get(|| async {})).layer(layer);
+
+        let request = Request::get("/")
+            .header("x-forwarded-for", "203.0.113.7")
+            .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))))
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let span = &collector.spans()[0];
+        assert_eq!(span.name, "custom");
+        assert_eq!(attribute(span, "http.request.method"), Some("GET".into()));
+        assert_eq!(
+            attribute(span, "http.response.status_code"),
+            Some(200.into())
+        );
+        assert_eq!(attribute(span, "http.method"), None);
+        assert_eq!(
+            attribute(span, "client.address"),
+            Some("203.0.113.7".into())
+        );
+    }
+}
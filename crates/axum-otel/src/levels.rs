@@ -0,0 +1,143 @@
+use axum::http::StatusCode;
+use std::{collections::HashMap, sync::Arc};
+use tracing::Level;
+
+/// Maps response status codes to the [`Level`] of the response and failure events.
+///
+/// Levels are set per status class, with overrides for single status codes. The
+/// default keeps successful requests at [`Level::DEBUG`] while failures stay visible:
+///
+/// | Status   | Level            |
+/// |----------|------------------|
+/// | `1xx`    | [`Level::DEBUG`] |
+/// | `2xx`    | [`Level::DEBUG`] |
+/// | `3xx`    | [`Level::DEBUG`] |
+/// | `4xx`    | [`Level::WARN`]  |
+/// | `5xx`    | [`Level::ERROR`] |
+///
+/// # Example
+///
+/// ```rust
+/// use axum::http::StatusCode;
+/// use axum_otel::{AxumOtelLayer, Level, StatusLevels};
+///
+/// let levels = StatusLevels::new()
+///     .success(Level::INFO)
+///     .status(StatusCode::NOT_FOUND, Level::DEBUG);
+///
+/// let layer = AxumOtelLayer::new().status_levels(levels);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StatusLevels {
+    informational: Level,
+    success: Level,
+    redirection: Level,
+    client_error: Level,
+    server_error: Level,
+    overrides: Arc<HashMap<StatusCode, Level>>,
+}
+
+impl StatusLevels {
+    /// Create a new `StatusLevels` with the default levels per status class.
+    pub fn new() -> Self {
+        Self {
+            informational: Level::DEBUG,
+            success: Level::DEBUG,
+            redirection: Level::DEBUG,
+            client_error: Level::WARN,
+            server_error: Level::ERROR,
+            overrides: Arc::default(),
+        }
+    }
+
+    /// Create a new `StatusLevels` that uses the same level for every status code.
+    pub fn uniform(level: Level) -> Self {
+        Self {
+            informational: level,
+            success: level,
+            redirection: level,
+            client_error: level,
+            server_error: level,
+            overrides: Arc::default(),
+        }
+    }
+
+    /// Set the level of `1xx` responses.
+    pub fn informational(mut self, level: Level) -> Self {
+        self.informational = level;
+        self
+    }
+
+    /// Set the level of `2xx` responses.
+    pub fn success(mut self, level: Level) -> Self {
+        self.success = level;
+        self
+    }
+
+    /// Set the level of `3xx` responses.
+    pub fn redirection(mut self, level: Level) -> Self {
+        self.redirection = level;
+        self
+    }
+
+    /// Set the level of `4xx` responses.
+    pub fn client_error(mut self, level: Level) -> Self {
+        self.client_error = level;
+        self
+    }
+
+    /// Set the level of `5xx` responses, and of failures without a status code.
+    pub fn server_error(mut self, level: Level) -> Self {
+        self.server_error = level;
+        self
+    }
+
+    /// Set the level of a single status code, overriding its class.
+    pub fn status(mut self, status: StatusCode, level: Level) -> Self {
+        Arc::make_mut(&mut self.overrides).insert(status, level);
+        self
+    }
+
+    /// Returns the [`Level`] for the response status code.
+    pub fn level_for(&self, status: StatusCode) -> Level {
+        if let Some(level) = self.overrides.get(&status) {
+            return *level;
+        }
+        match status.as_u16() {
+            100..=199 => self.informational,
+            200..=299 => self.success,
+            300..=399 => self.redirection,
+            400..=499 => self.client_error,
+            _ => self.server_error,
+        }
+    }
+
+    /// Returns the [`Level`] for failures without a status code, such as errors.
+    pub(crate) fn error_level(&self) -> Level {
+        self.server_error
+    }
+}
+
+impl Default for StatusLevels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_for() {
+        let levels = StatusLevels::new().status(StatusCode::NOT_FOUND, Level::DEBUG);
+        assert_eq!(levels.level_for(StatusCode::OK), Level::DEBUG);
+        assert_eq!(levels.level_for(StatusCode::NOT_FOUND), Level::DEBUG);
+        assert_eq!(levels.level_for(StatusCode::BAD_REQUEST), Level::WARN);
+        assert_eq!(levels.level_for(StatusCode::BAD_GATEWAY), Level::ERROR);
+        assert_eq!(
+            StatusLevels::uniform(Level::INFO).level_for(StatusCode::BAD_GATEWAY),
+            Level::INFO
+        );
+    }
+}
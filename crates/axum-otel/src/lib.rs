@@ -22,8 +22,25 @@
 //! - Automatic request and response tracing
 //! - OpenTelemetry integration
 //! - Request ID tracking
-//! - Customizable span attributes
-//! - Error tracking
+//! - Customizable span attributes, names and enrichment callbacks
+//! - Legacy, stable or dual-emit HTTP semantic conventions
+//! - Request and response header capture with redaction
+//! - Skipping or downgrading health checks and probes
+//! - Client ip extraction behind trusted proxies
+//! - Trace context in response headers (`traceresponse`, `x-trace-id`, `Server-Timing`)
+//! - Size-limited request and response body capture with redaction
+//! - Configurable span status mapping for HTTP and gRPC
+//! - Response and failure event levels by status class, with per-status overrides
+//! - Extractors for the trace context of the request ([`TraceId`], [`TraceParent`], [`OtelContext`])
+//! - Time to first byte, duration and size of streamed response bodies
+//! - WebSocket connection and message tracing (requires the `ws` feature)
+//! - Error tracking, including handler errors reported with [`ErrorReport`]
+//! - Timeout, load-shed and rate-limit rejections recorded with [`MiddlewareRejection`]
+//! - Panic capture that marks the request span as failed
+//! - Detection of requests cancelled by client disconnects
+//! - Slow-request events with global and per-route latency thresholds
+//! - Common, combined or JSON access log events under a dedicated target
+//! - HTTP server metrics with a cardinality limit on attribute values
 //!
 //! ## Usage
 //!
@@ -50,22 +67,123 @@
 //!     );
 //! ```
 //!
+//! The same stack, including request id handling, can be added in one call with
+//! [`AxumOtelRouterExt::with_otel`] or [`AxumOtelLayer`]:
+//!
+//! ```rust
+//! use axum::{routing::get, Router};
+//! use axum_otel::AxumOtelRouterExt;
+//!
+//! async fn handler() -> &'static str {
+//!     "Hello, world!"
+//! }
+//!
+//! let app: Router<()> = Router::new().route("/", get(handler)).with_otel();
+//! ```
+//!
 //! ## Components
 //!
+//! - [`AxumOtelLayer`] - Bundles request id handling and the trace layer below
 //! - [`AxumOtelSpanCreator`] - Creates spans for each request with relevant HTTP information
 //! - [`AxumOtelOnResponse`] - Records response status and latency
+//! - [`AxumOtelOnBodyChunk`] and [`AxumOtelOnEos`] - Record time to first byte and the
+//!   lifecycle of streamed response bodies, with [`ResponseBodyLayer`] tracking their end
 //! - [`AxumOtelOnFailure`] - Handles error cases and updates span status
+//! - [`HttpMetricsLayer`] - Records the semantic-convention HTTP server metrics
+//! - [`BodyCaptureLayer`] - Records the first bytes of request and response bodies
+//! - [`CatchPanicLayer`] - Records panics on the request span and responds with a `500`
+//! - [`AccessLogLayer`] - Emits one access log event per request
+//! - [`CancellationLayer`] - Records requests cancelled before the response was ready
+//! - [`TraceContextResponseLayer`] - Writes the trace context into the response headers
 //!
 //! See the [examples](https://github.com/iamnivekx/axum-otel/tree/main/examples) directory for complete examples.
 //!
+mod access_log;
+mod body_capture;
+mod cancel;
+mod cardinality;
+mod enrich;
+mod error;
+mod extract;
+mod filter;
+mod headers;
+mod layer;
+mod levels;
 mod make_span;
+mod metrics;
+mod on_body;
 mod on_failure;
 mod on_response;
+mod panic;
+mod rejection;
+mod request;
+mod router;
+mod semconv;
+mod slow;
+mod status;
+#[cfg(test)]
+mod testing;
+mod trace_response;
+#[cfg(feature = "ws")]
+mod ws;
 
 // Exports for the tower-http::trace::TraceLayer based middleware
+pub use levels::StatusLevels;
 pub use make_span::AxumOtelSpanCreator;
+pub use on_body::{AxumOtelOnBodyChunk, AxumOtelOnEos, ResponseBodyLayer, TrackResponseBody};
 pub use on_failure::AxumOtelOnFailure;
 pub use on_response::AxumOtelOnResponse;
+pub use semconv::HttpSemConv;
+pub use slow::SlowRequests;
+pub use status::{SpanStatus, StatusPolicy};
+
+// Exports for span enrichment
+pub use enrich::{SpanAttributes, ENRICHMENT_FIELDS};
+
+// Exports for the trace context extractors
+pub use extract::{
+    InsertOtelContext, OtelContext, OtelContextLayer, SpanId, TraceContextRejection, TraceId,
+    TraceParent,
+};
+
+// Exports for error reporting
+pub use error::ErrorReport;
+pub use rejection::MiddlewareRejection;
+
+// Exports for panic capture
+pub use panic::{install_panic_hook, CatchPanic, CatchPanicLayer};
+
+// Exports for access logs
+pub use access_log::{AccessLog, AccessLogFormat, AccessLogLayer, ACCESS_LOG_TARGET};
+
+// Exports for cancellation detection
+pub use cancel::{CancellationLayer, DetectCancellation};
+
+// Exports for header capture
+pub use headers::{HeaderCapture, Redaction};
+
+// Exports for body capture
+pub use body_capture::{BodyCapture, BodyCaptureLayer};
+
+// Exports for request filtering
+pub use filter::{FilterAction, RequestFilter};
+pub use request::{RequestInfo, ResponseInfo};
+
+// Exports for the bundled middleware stack
+pub use layer::{AxumOtelLayer, AxumOtelMakeRequestId, AxumOtelService, AxumOtelTrace};
+pub use router::AxumOtelRouterExt;
+
+// Exports for the HTTP server metrics middleware
+pub use metrics::{HttpMetrics, HttpMetricsLayer};
+
+// Exports for the trace context response headers
+pub use trace_response::{
+    TraceContextResponse, TraceContextResponseLayer, SERVER_TIMING, TRACERESPONSE, X_TRACE_ID,
+};
+
+// Exports for WebSocket tracing
+#[cfg(feature = "ws")]
+pub use ws::{TracedWebSocket, WebSocketTracing};
 
 // Re-export the Level enum from tracing crate
 pub use tracing::Level;
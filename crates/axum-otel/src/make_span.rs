@@ -1,16 +1,34 @@
+use crate::{
+    enrich::{RequestEnrichers, SpanAttributes, ENRICHMENT_FIELDS},
+    headers::{HeaderCapture, REQUEST_HEADER_PREFIX},
+    FilterAction, HttpSemConv, RequestFilter, RequestInfo,
+};
 use axum::{
     extract::{ConnectInfo, MatchedPath},
     http,
 };
-use opentelemetry::trace::SpanKind;
-use std::net::SocketAddr;
-use tower_http::trace::MakeSpan;
-use tracing::{field::Empty, Level};
+use std::{fmt, net::SocketAddr, sync::Arc};
+use tower_http::{request_id::RequestId, trace::MakeSpan};
+use tracing::{
+    field::{display, Empty},
+    Level,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_otel_extra::{
-    dyn_span,
-    extract::{context, fields},
+    extract::{context, fields, fields::TrustedProxies, schema, span::record_http_fields},
+    request_span,
 };
 
+/// A user supplied strategy for naming request spans.
+#[derive(Clone)]
+struct SpanNamer(Arc<dyn Fn(&RequestInfo<'_>) -> String + Send + Sync>);
+
+impl fmt::Debug for SpanNamer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SpanNamer").finish_non_exhaustive()
+    }
+}
+
 /// An implementor of [`MakeSpan`] which creates `tracing` spans populated with information about
 /// the request received by an `axum` web server.
 ///
@@ -19,12 +37,23 @@ use tracing_otel_extra::{
 /// This span creator automatically adds the following attributes to each span:
 ///
 /// - `http.method`: The HTTP method
-/// - `http.route`: The matched route
-/// - `http.client_ip`: The client's IP address
+/// - `http.route`: The matched route, see [`AxumOtelSpanCreator::unmatched_route`]
+/// - `http.client_ip`: The client's IP address, see [`AxumOtelSpanCreator::trusted_proxies`]
+/// - `network.peer.address` and `network.peer.port`: The socket peer address, which is
+///   the nearest proxy when running behind one
 /// - `http.host`: The Host header
 /// - `http.user_agent`: The User-Agent header
-/// - `request_id`: A unique request identifier
+/// - `request_id`: A unique request identifier, taken from the [`RequestId`] extension
+///   when present and from the `x-request-id` or `request-id` headers otherwise
 /// - `trace_id`: The OpenTelemetry trace ID
+/// - `http.request.header.<name>`: The request headers selected with
+///   [`AxumOtelSpanCreator::request_headers`]
+/// - [`ENRICHMENT_FIELDS`]: Extra attributes recorded with [`AxumOtelSpanCreator::enrich`]
+///
+/// Attributes missing from the request, like an absent `user-agent` header, are left unset.
+///
+/// The HTTP attributes above follow the legacy semantic conventions by default, use
+/// [`AxumOtelSpanCreator::semconv`] to record the stable conventions instead or as well.
 ///
 /// # Example
 ///
@@ -35,9 +64,16 @@ use tracing_otel_extra::{
 /// let layer = TraceLayer::new_for_http()
 ///     .make_span_with(AxumOtelSpanCreator::new().level(Level::INFO));
 /// ```
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct AxumOtelSpanCreator {
     level: Level,
+    semconv: HttpSemConv,
+    request_headers: Option<HeaderCapture>,
+    filter: Option<RequestFilter>,
+    trusted_proxies: Arc<TrustedProxies>,
+    span_name: Option<SpanNamer>,
+    enrichers: RequestEnrichers,
+    unmatched_route: Option<Arc<str>>,
 }
 
 impl AxumOtelSpanCreator {
@@ -45,6 +81,13 @@ impl AxumOtelSpanCreator {
     pub fn new() -> Self {
         Self {
             level: Level::TRACE,
+            semconv: HttpSemConv::Legacy,
+            request_headers: None,
+            filter: None,
+            trusted_proxies: Arc::default(),
+            span_name: None,
+            enrichers: RequestEnrichers::default(),
+            unmatched_route: None,
         }
     }
 
@@ -57,6 +100,120 @@ impl AxumOtelSpanCreator {
         self.level = level;
         self
     }
+
+    /// Set the [`HttpSemConv`] used for the span attributes.
+    ///
+    /// Defaults to [`HttpSemConv::Legacy`].
+    pub fn semconv(mut self, semconv: HttpSemConv) -> Self {
+        self.semconv = semconv;
+        self
+    }
+
+    /// Record the request headers in the [`HeaderCapture`] allowlist as
+    /// `http.request.header.<name>` attributes.
+    ///
+    /// By default no request headers are captured.
+    pub fn request_headers(mut self, headers: HeaderCapture) -> Self {
+        self.request_headers = Some(headers);
+        self
+    }
+
+    /// Skip or downgrade the span for requests matched by the [`RequestFilter`].
+    ///
+    /// Skipped requests get a disabled span, and [`AxumOtelOnResponse`] and
+    /// [`AxumOtelOnFailure`] emit no events for them.
+    ///
+    /// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+    /// [`AxumOtelOnFailure`]: crate::AxumOtelOnFailure
+    pub fn filter(mut self, filter: RequestFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Set the [`TrustedProxies`] whose forwarding headers are used to find the client ip.
+    ///
+    /// When the socket peer is a trusted proxy, the client ip is taken from the
+    /// `Forwarded`, `X-Forwarded-For` or `X-Real-IP` headers, see
+    /// [`fields::extract_client_ip`]. The peer address requires the router to be served
+    /// with [`ConnectInfo`].
+    ///
+    /// By default no proxies are trusted and the socket peer is the client.
+    pub fn trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = Arc::new(trusted_proxies);
+        self
+    }
+
+    /// Name request spans with the given closure instead of `"{method} {route}"`.
+    ///
+    /// The closure receives the request, including the route it matched through
+    /// [`RequestInfo::matched_path`]. The name is recorded as `otel.name`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use axum_otel::AxumOtelSpanCreator;
+    ///
+    /// let make_span = AxumOtelSpanCreator::new().span_name(|request| {
+    ///     format!(
+    ///         "users-service {} {}",
+    ///         request.method(),
+    ///         request.matched_path().unwrap_or("unmatched")
+    ///     )
+    /// });
+    /// ```
+    pub fn span_name<F>(mut self, span_name: F) -> Self
+    where
+        F: Fn(&RequestInfo<'_>) -> String + Send + Sync + 'static,
+    {
+        self.span_name = Some(SpanNamer(Arc::new(span_name)));
+        self
+    }
+
+    /// Record requests that matched no route, such as `404`s, with the given
+    /// `http.route`.
+    ///
+    /// The span name keeps falling back to the method for these requests. By default
+    /// `http.route` is left empty.
+    pub fn unmatched_route(mut self, route: impl Into<Arc<str>>) -> Self {
+        self.unmatched_route = Some(route.into());
+        self
+    }
+
+    /// Record extra attributes on the request span with the given callback.
+    ///
+    /// The callback runs once the span is created, and can read the request
+    /// extensions inserted by earlier middleware such as authentication. Attributes in
+    /// [`ENRICHMENT_FIELDS`] are recorded as span fields, see [`SpanAttributes`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use axum_otel::AxumOtelSpanCreator;
+    ///
+    /// #[derive(Clone)]
+    /// struct CurrentUser {
+    ///     id: String,
+    ///     tenant: String,
+    /// }
+    ///
+    /// let make_span = AxumOtelSpanCreator::new().enrich(|request, attributes| {
+    ///     if let Some(user) = request.extensions().get::<CurrentUser>() {
+    ///         attributes
+    ///             .record("enduser.id", user.id.clone())
+    ///             .record("tenant.id", user.tenant.clone());
+    ///     }
+    ///     if let Some(version) = request.headers().get("x-api-version") {
+    ///         attributes.record("api.version", version.to_str().unwrap_or_default().to_owned());
+    ///     }
+    /// });
+    /// ```
+    pub fn enrich<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&RequestInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync + 'static,
+    {
+        self.enrichers.push(Arc::new(callback));
+        self
+    }
 }
 
 impl Default for AxumOtelSpanCreator {
@@ -67,41 +224,153 @@ impl Default for AxumOtelSpanCreator {
 
 impl<B> MakeSpan<B> for AxumOtelSpanCreator {
     fn make_span(&mut self, request: &http::Request<B>) -> tracing::Span {
+        let level = match self
+            .filter
+            .as_ref()
+            .and_then(|filter| filter.evaluate(&RequestInfo::new(request)))
+        {
+            Some(FilterAction::Skip) => return tracing::Span::none(),
+            Some(FilterAction::Level(level)) => level,
+            None => self.level,
+        };
+        let legacy = self.semconv.emit_legacy();
+        let stable = self.semconv.emit_stable();
+
         let http_method = request.method().as_str();
+        let http_request_method = fields::normalize_http_method(request.method());
         let http_route = request
             .extensions()
             .get::<MatchedPath>()
             .map(|p| p.as_str());
 
-        let client_ip = request
+        let peer_addr = request
             .extensions()
             .get::<ConnectInfo<SocketAddr>>()
-            .map(|ConnectInfo(ip)| tracing::field::debug(ip));
-
-        let span_name = http_route.as_ref().map_or_else(
-            || http_method.to_string(),
-            |route| format!("{} {}", http_method, route),
+            .map(|ConnectInfo(addr)| *addr);
+        let client_ip = fields::extract_client_ip(
+            request.headers(),
+            peer_addr.map(|addr| addr.ip()),
+            &self.trusted_proxies,
         );
+        let (server_address, server_port) = fields::extract_server_address(request);
+
+        let request_id = request
+            .extensions()
+            .get::<RequestId>()
+            .and_then(|id| id.header_value().to_str().ok())
+            .or_else(|| fields::extract_request_id_from_headers(request.headers()));
+
+        // The stable conventions name spans after the normalized method
+        let span_method = if stable {
+            http_request_method
+        } else {
+            http_method
+        };
+        let span_name = match &self.span_name {
+            Some(SpanNamer(span_name)) => span_name(&RequestInfo::new(request)),
+            None => http_route.as_ref().map_or_else(
+                || span_method.to_string(),
+                |route| format!("{} {}", span_method, route),
+            ),
+        };
 
-        let span = dyn_span!(
-            self.level,
-            "request",
-            http.client_ip = client_ip,
-            http.versions = ?request.version(),
-            http.host = ?fields::extract_host(request),
-            http.method = ?fields::extract_http_method(request),
-            http.route = http_route,
-            http.scheme = ?fields::extract_http_scheme(request),
-            http.status_code = Empty,
-            http.target = request.uri().path_and_query().map(|p| p.as_str()),
-            http.user_agent = ?fields::extract_user_agent(request),
-            otel.name = span_name,
-            otel.kind = ?SpanKind::Server,
-            otel.status_code = Empty,
-            request_id = %fields::extract_request_id(request),
-            trace_id = Empty
+        let span = request_span!(
+            level,
+            span_name,
+            { schema::HTTP_REQUEST_METHOD } = stable.then_some(http_request_method),
+            { schema::HTTP_RESPONSE_STATUS_CODE } = Empty,
+            { schema::URL_PATH } = stable.then(|| fields::extract_url_path(request)),
+            { schema::URL_QUERY } = fields::extract_url_query(request).filter(|_| stable),
+            { schema::URL_SCHEME } =
+                stable.then(|| fields::extract_http_scheme(request).unwrap_or("http")),
+            { schema::CLIENT_ADDRESS } = client_ip.filter(|_| stable).map(display),
+            { schema::SERVER_ADDRESS } = server_address.filter(|_| stable),
+            { schema::SERVER_PORT } = server_port.filter(|_| stable).map(i64::from),
+            network.peer.address = peer_addr.map(|addr| display(addr.ip())),
+            { schema::NETWORK_PROTOCOL_VERSION } =
+                fields::extract_network_protocol_version(request).filter(|_| stable),
+            { schema::USER_AGENT_ORIGINAL } =
+                fields::extract_user_agent(request).filter(|_| stable),
+            enduser.id = Empty,
+            tenant.id = Empty,
+            api.version = Empty,
+            feature_flag.variant = Empty
         );
+        if legacy {
+            record_http_fields(&span, request);
+            if let Some(client_ip) = client_ip {
+                span.record(schema::HTTP_CLIENT_IP, display(client_ip));
+            }
+        }
+        if let Some(route) = http_route.or(self.unmatched_route.as_deref()) {
+            span.record(schema::HTTP_ROUTE, route);
+        }
+        if let Some(request_id) = request_id {
+            span.record(schema::REQUEST_ID, request_id);
+        }
         context::set_otel_parent(request.headers(), &span);
+        // Recorded on the OpenTelemetry span only, tracing spans are limited to 32 fields
+        if stable && http_request_method != http_method {
+            span.set_attribute(schema::HTTP_REQUEST_METHOD_ORIGINAL, http_method.to_owned());
+        }
+        if let Some(addr) = peer_addr {
+            span.set_attribute("network.peer.port", i64::from(addr.port()));
+        }
+        if let Some(headers) = &self.request_headers {
+            headers.record(REQUEST_HEADER_PREFIX, request.headers(), &span);
+        }
+        if !span.is_disabled() {
+            self.enrichers.enrich(&RequestInfo::new(request), &span);
+        }
         span
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::SpanCollector;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+    use tower_http::trace::TraceLayer;
+
+    async fn span_name(make_span: AxumOtelSpanCreator, uri: &str) -> String {
+        let collector = SpanCollector::install();
+        let app = Router::new()
+            .route("/users/{id}", get(|| async {}))
+            .layer(TraceLayer::new_for_http().make_span_with(make_span));
+        let request = http::Request::get(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let spans = collector.spans();
+        assert_eq!(spans.len(), 1);
+        spans[0].name.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_span_name_is_method_and_route() {
+        let name = span_name(AxumOtelSpanCreator::new(), "/users/42").await;
+        assert_eq!(name, "GET /users/{id}");
+    }
+
+    #[tokio::test]
+    async fn test_span_name_falls_back_to_method() {
+        let name = span_name(AxumOtelSpanCreator::new(), "/missing").await;
+        assert_eq!(name, "GET");
+
+        let make_span = AxumOtelSpanCreator::new().unmatched_route("unmatched");
+        assert_eq!(span_name(make_span, "/missing").await, "GET");
+    }
+
+    #[tokio::test]
+    async fn test_custom_span_name() {
+        let make_span = AxumOtelSpanCreator::new().span_name(|request| {
+            format!(
+                "users-service {}",
+                request.matched_path().unwrap_or("unmatched")
+            )
+        });
+        let name = span_name(make_span, "/users/42").await;
+        assert_eq!(name, "users-service /users/{id}");
+    }
+}
@@ -0,0 +1,573 @@
+use crate::{cardinality::CardinalityLimiter, MiddlewareRejection};
+use axum::{
+    body::HttpBody,
+    extract::MatchedPath,
+    http::{self, HeaderMap, Request, Response},
+};
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram, Meter, UpDownCounter},
+    KeyValue,
+};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+use tracing_otel_extra::extract::{fields, schema};
+
+/// The name of the meter used to create the HTTP server instruments.
+const METER_NAME: &str = "axum-otel";
+
+/// The names of the HTTP server instruments.
+const REQUEST_DURATION: &str = "http.server.request.duration";
+const ACTIVE_REQUESTS: &str = "http.server.active_requests";
+const REQUEST_BODY_SIZE: &str = "http.server.request.body.size";
+const RESPONSE_BODY_SIZE: &str = "http.server.response.body.size";
+
+/// The default number of distinct values per attribute, see
+/// [`HttpMetricsLayer::max_attribute_values`].
+const DEFAULT_MAX_ATTRIBUTE_VALUES: usize = 1000;
+
+/// Bucket boundaries for `http.server.request.duration` recommended by the semantic conventions.
+const DURATION_BOUNDARIES: [f64; 14] = [
+    0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 7.5, 10.0,
+];
+
+/// An instrument and the cardinality limiter of its attributes.
+#[derive(Debug)]
+struct Limited<I> {
+    instrument: I,
+    limiter: CardinalityLimiter,
+}
+
+impl<I> Limited<I> {
+    fn new(name: &'static str, instrument: I, max_values: usize, overflow: &Counter<u64>) -> Self {
+        Self {
+            instrument,
+            limiter: CardinalityLimiter::new(name, max_values, overflow.clone()),
+        }
+    }
+}
+
+/// The instruments for the semantic-convention HTTP server metrics.
+#[derive(Debug)]
+struct HttpServerInstruments {
+    request_duration: Limited<Histogram<f64>>,
+    active_requests: Limited<UpDownCounter<i64>>,
+    request_body_size: Limited<Histogram<u64>>,
+    response_body_size: Limited<Histogram<u64>>,
+}
+
+impl HttpServerInstruments {
+    fn new(meter: &Meter, max_values: usize) -> Self {
+        let overflow = meter
+            .u64_counter("axum_otel.cardinality.overflow")
+            .with_description(
+                "Number of metric attribute values replaced because of the cardinality limit.",
+            )
+            .with_unit("{value}")
+            .build();
+        let request_duration = meter
+            .f64_histogram(REQUEST_DURATION)
+            .with_description("Duration of HTTP server requests.")
+            .with_unit("s")
+            .with_boundaries(DURATION_BOUNDARIES.to_vec())
+            .build();
+        let active_requests = meter
+            .i64_up_down_counter(ACTIVE_REQUESTS)
+            .with_description("Number of active HTTP server requests.")
+            .with_unit("{request}")
+            .build();
+        let request_body_size = meter
+            .u64_histogram(REQUEST_BODY_SIZE)
+            .with_description("Size of HTTP server request bodies.")
+            .with_unit("By")
+            .build();
+        let response_body_size = meter
+            .u64_histogram(RESPONSE_BODY_SIZE)
+            .with_description("Size of HTTP server response bodies.")
+            .with_unit("By")
+            .build();
+
+        Self {
+            request_duration: Limited::new(
+                REQUEST_DURATION,
+                request_duration,
+                max_values,
+                &overflow,
+            ),
+            active_requests: Limited::new(ACTIVE_REQUESTS, active_requests, max_values, &overflow),
+            request_body_size: Limited::new(
+                REQUEST_BODY_SIZE,
+                request_body_size,
+                max_values,
+                &overflow,
+            ),
+            response_body_size: Limited::new(
+                RESPONSE_BODY_SIZE,
+                response_body_size,
+                max_values,
+                &overflow,
+            ),
+        }
+    }
+}
+
+/// A [`Layer`] that records the semantic-convention HTTP server metrics.
+///
+/// The following instruments are created on the global meter provider, which is
+/// installed by `init_meter_provider` from `tracing-opentelemetry-extra`:
+///
+/// - `http.server.request.duration`: Histogram of request durations in seconds
+/// - `http.server.active_requests`: Number of requests currently being processed
+/// - `http.server.request.body.size`: Histogram of request body sizes in bytes
+/// - `http.server.response.body.size`: Histogram of response body sizes in bytes
+///
+/// Measurements carry the `http.request.method`, `http.route`, `http.response.status_code`
+/// and `url.scheme` attributes, plus `error.type` for `5xx` responses and
+/// [`MiddlewareRejection`]s. Body sizes are taken from the body size hint or the
+/// `Content-Length` header and are skipped when neither is known.
+///
+/// The instruments are created when the layer is constructed, so the meter provider
+/// must be installed before that.
+///
+/// To keep the cardinality bounded under scanners, each string attribute of each
+/// instrument admits at most [`HttpMetricsLayer::max_attribute_values`] distinct values.
+/// Later values are recorded as `__other__` and counted by the
+/// `axum_otel.cardinality.overflow` counter, with the `metric` and `attribute` it
+/// happened on. Numeric attributes such as `http.response.status_code` are bounded and
+/// never replaced. Requests that matched no route carry no `http.route`, or the route set with
+/// [`HttpMetricsLayer::unmatched_route`].
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::HttpMetricsLayer;
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .layer(HttpMetricsLayer::new());
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpMetricsLayer {
+    meter: Meter,
+    instruments: Arc<HttpServerInstruments>,
+    unmatched_route: Option<Arc<str>>,
+}
+
+impl HttpMetricsLayer {
+    /// Create a new `HttpMetricsLayer` using the global meter provider.
+    pub fn new() -> Self {
+        Self::with_meter(global::meter(METER_NAME))
+    }
+
+    /// Create a new `HttpMetricsLayer` recording on the given [`Meter`], for example
+    /// one from a meter provider that is not installed globally.
+    pub fn with_meter(meter: Meter) -> Self {
+        Self {
+            instruments: Arc::new(HttpServerInstruments::new(
+                &meter,
+                DEFAULT_MAX_ATTRIBUTE_VALUES,
+            )),
+            meter,
+            unmatched_route: None,
+        }
+    }
+
+    /// Set the maximum number of distinct values recorded for each string attribute of
+    /// each instrument.
+    ///
+    /// Defaults to `1000`.
+    pub fn max_attribute_values(mut self, max_values: usize) -> Self {
+        self.instruments = Arc::new(HttpServerInstruments::new(&self.meter, max_values));
+        self
+    }
+
+    /// Record requests that matched no route, such as `404`s, with the given
+    /// `http.route`.
+    ///
+    /// By default `http.route` is left out for these requests.
+    pub fn unmatched_route(mut self, route: impl Into<Arc<str>>) -> Self {
+        self.unmatched_route = Some(route.into());
+        self
+    }
+}
+
+impl Default for HttpMetricsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for HttpMetricsLayer {
+    type Service = HttpMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpMetrics {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that records the semantic-convention HTTP server metrics.
+///
+/// See [`HttpMetricsLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct HttpMetrics<S> {
+    inner: S,
+    layer: HttpMetricsLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for HttpMetrics<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ReqBody: HttpBody,
+    ResBody: HttpBody,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let mut attributes = vec![
+            KeyValue::new(
+                schema::HTTP_REQUEST_METHOD,
+                fields::normalize_http_method(request.method()),
+            ),
+            KeyValue::new(
+                schema::URL_SCHEME,
+                fields::extract_http_scheme(&request)
+                    .unwrap_or("http")
+                    .to_owned(),
+            ),
+        ];
+        let active_request = ActiveRequest::new(self.layer.instruments.clone(), &attributes);
+
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|route| route.as_str().to_owned())
+            .or_else(|| self.layer.unmatched_route.as_deref().map(str::to_owned));
+        if let Some(route) = route {
+            attributes.push(KeyValue::new(schema::HTTP_ROUTE, route));
+        }
+        let request_body_size = body_size(request.body(), request.headers());
+
+        ResponseFuture {
+            inner: self.inner.call(request),
+            state: Some(RequestMetrics {
+                active_request,
+                attributes,
+                request_body_size,
+                start: Instant::now(),
+            }),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`HttpMetrics`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        state: Option<RequestMetrics>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: HttpBody,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.inner.poll(cx));
+
+        if let Some(state) = this.state.take() {
+            match &result {
+                Ok(response) => state.record(
+                    Some(response.status()),
+                    response.extensions().get::<MiddlewareRejection>(),
+                    body_size(response.body(), response.headers()),
+                ),
+                Err(_) => state.record(None, None, None),
+            }
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+/// The measurements of a single request, recorded once the response is ready.
+struct RequestMetrics {
+    active_request: ActiveRequest,
+    attributes: Vec<KeyValue>,
+    request_body_size: Option<u64>,
+    start: Instant,
+}
+
+impl RequestMetrics {
+    fn record(
+        mut self,
+        status: Option<http::StatusCode>,
+        rejection: Option<&MiddlewareRejection>,
+        response_body_size: Option<u64>,
+    ) {
+        let instruments = &self.active_request.instruments;
+        match status {
+            Some(status) => {
+                self.attributes.push(KeyValue::new(
+                    schema::HTTP_RESPONSE_STATUS_CODE,
+                    i64::from(status.as_u16()),
+                ));
+                if let Some(rejection) = rejection {
+                    self.attributes
+                        .push(KeyValue::new("error.type", rejection.error_type()));
+                } else if status.is_server_error() {
+                    self.attributes
+                        .push(KeyValue::new("error.type", status.as_str().to_owned()));
+                }
+            }
+            None => self.attributes.push(KeyValue::new("error.type", "_OTHER")),
+        }
+
+        let Limited {
+            instrument,
+            limiter,
+        } = &instruments.request_duration;
+        instrument.record(
+            self.start.elapsed().as_secs_f64(),
+            &limiter.limit(&self.attributes),
+        );
+        if let Some(size) = self.request_body_size {
+            let Limited {
+                instrument,
+                limiter,
+            } = &instruments.request_body_size;
+            instrument.record(size, &limiter.limit(&self.attributes));
+        }
+        if let Some(size) = response_body_size {
+            let Limited {
+                instrument,
+                limiter,
+            } = &instruments.response_body_size;
+            instrument.record(size, &limiter.limit(&self.attributes));
+        }
+    }
+}
+
+/// Tracks a request in `http.server.active_requests` until it is dropped.
+///
+/// Decrementing on drop keeps the counter correct when the response future is
+/// cancelled before it completes.
+struct ActiveRequest {
+    instruments: Arc<HttpServerInstruments>,
+    attributes: Vec<KeyValue>,
+}
+
+impl ActiveRequest {
+    fn new(instruments: Arc<HttpServerInstruments>, attributes: &[KeyValue]) -> Self {
+        let active_requests = &instruments.active_requests;
+        // Keep the limited attributes, so the decrement matches the increment
+        let attributes = active_requests.limiter.limit(attributes).into_owned();
+        active_requests.instrument.add(1, &attributes);
+        Self {
+            instruments,
+            attributes,
+        }
+    }
+}
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        self.instruments
+            .active_requests
+            .instrument
+            .add(-1, &self.attributes);
+    }
+}
+
+/// Returns the body size from the exact size hint, or from the `Content-Length` header.
+pub(crate) fn body_size<B: HttpBody>(body: &B, headers: &HeaderMap) -> Option<u64> {
+    body.size_hint().exact().or_else(|| {
+        fields::extract_field_from_headers(headers, http::header::CONTENT_LENGTH)
+            .and_then(|value| value.parse().ok())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MetricCollector;
+    use axum::{
+        body::{Body, Bytes},
+        http::StatusCode,
+        response::IntoResponse,
+        routing::get,
+        Router,
+    };
+    use opentelemetry::Value;
+    use std::{convert::Infallible, time::Duration};
+    use tower::ServiceExt;
+
+    /// A body that yields one chunk without announcing its size.
+    struct UnsizedBody(Option<Bytes>);
+
+    impl http_body::Body for UnsizedBody {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<http_body::Frame<Bytes>, Infallible>>> {
+            Poll::Ready(self.0.take().map(|chunk| Ok(http_body::Frame::data(chunk))))
+        }
+    }
+
+    fn unsized_body() -> Body {
+        Body::new(UnsizedBody(Some(Bytes::from_static(b"hello"))))
+    }
+
+    fn app(layer: HttpMetricsLayer) -> Router {
+        Router::new()
+            .route("/users/{id}", get(|| async { "ok" }))
+            .route("/fail", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .route(
+                "/limited",
+                get(|| async { MiddlewareRejection::RateLimited.into_response() }),
+            )
+            .route("/stream", get(|| async { unsized_body() }))
+            .layer(layer)
+    }
+
+    async fn send(app: Router, request: Request<Body>) -> StatusCode {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        status
+    }
+
+    #[tokio::test]
+    async fn test_request_is_recorded() {
+        let collector = MetricCollector::new();
+        let app = app(HttpMetricsLayer::with_meter(collector.meter()));
+        let request = Request::get("/users/42").body(Body::from("hello")).unwrap();
+        assert_eq!(send(app, request).await, StatusCode::OK);
+
+        let durations = collector.data_points(REQUEST_DURATION);
+        assert_eq!(durations.len(), 1);
+        let point = &durations[0];
+        assert_eq!(point.value, 1.0);
+        assert_eq!(point.attribute("http.request.method"), Some("GET".into()));
+        assert_eq!(point.attribute("http.route"), Some("/users/{id}".into()));
+        assert_eq!(
+            point.attribute("http.response.status_code"),
+            Some(Value::I64(200))
+        );
+        assert_eq!(point.attribute("url.scheme"), Some("http".into()));
+        assert_eq!(point.attribute("error.type"), None);
+
+        assert_eq!(collector.data_points(REQUEST_BODY_SIZE).len(), 1);
+        assert_eq!(collector.data_points(RESPONSE_BODY_SIZE).len(), 1);
+        assert_eq!(collector.data_points(ACTIVE_REQUESTS)[0].value, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_body_sizes_are_skipped() {
+        let collector = MetricCollector::new();
+        let app = app(HttpMetricsLayer::with_meter(collector.meter()));
+        let request = Request::get("/stream").body(unsized_body()).unwrap();
+        assert_eq!(send(app, request).await, StatusCode::OK);
+
+        assert_eq!(collector.data_points(REQUEST_DURATION).len(), 1);
+        assert!(collector.data_points(REQUEST_BODY_SIZE).is_empty());
+        assert!(collector.data_points(RESPONSE_BODY_SIZE).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_is_no_longer_active() {
+        let collector = MetricCollector::new();
+        let service = HttpMetricsLayer::with_meter(collector.meter()).layer(tower::service_fn(
+            |_: Request<Body>| async {
+                std::future::pending::<()>().await;
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            },
+        ));
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let mut future = Box::pin(service.oneshot(request));
+        let timeout = tokio::time::timeout(Duration::from_millis(10), &mut future);
+        assert!(timeout.await.is_err());
+        assert_eq!(collector.data_points(ACTIVE_REQUESTS)[0].value, 1.0);
+
+        drop(future);
+        assert_eq!(collector.data_points(ACTIVE_REQUESTS)[0].value, 0.0);
+        assert!(collector.data_points(REQUEST_DURATION).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_type_is_recorded() {
+        let collector = MetricCollector::new();
+        for uri in ["/fail", "/limited"] {
+            let app = app(HttpMetricsLayer::with_meter(collector.meter()));
+            send(app, Request::get(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let error_types: Vec<_> = collector
+            .data_points(REQUEST_DURATION)
+            .iter()
+            .map(|point| point.attribute("error.type"))
+            .collect();
+        assert_eq!(error_types.len(), 2);
+        assert!(error_types.contains(&Some("500".into())));
+        assert!(error_types.contains(&Some("rate_limited".into())));
+    }
+
+    #[tokio::test]
+    async fn test_routes_over_the_limit_overflow() {
+        let collector = MetricCollector::new();
+        let layer = HttpMetricsLayer::with_meter(collector.meter()).max_attribute_values(1);
+        for uri in ["/users/1", "/fail"] {
+            send(
+                app(layer.clone()),
+                Request::get(uri).body(Body::empty()).unwrap(),
+            )
+            .await;
+        }
+
+        let durations = collector.data_points(REQUEST_DURATION);
+        let routes: Vec<_> = durations
+            .iter()
+            .map(|point| point.attribute("http.route").unwrap().as_str().into_owned())
+            .collect();
+        assert_eq!(routes.len(), 2);
+        assert!(routes.contains(&"/users/{id}".to_owned()));
+        assert!(routes.contains(&crate::cardinality::OVERFLOW_VALUE.to_owned()));
+        assert!(durations.iter().all(|point| matches!(
+            point.attribute("http.response.status_code"),
+            Some(Value::I64(_))
+        )));
+        let overflow = collector.data_points("axum_otel.cardinality.overflow");
+        assert_eq!(overflow[0].value, 1.0);
+    }
+}
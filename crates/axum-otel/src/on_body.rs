@@ -0,0 +1,558 @@
+use axum::{
+    body::{Bytes, HttpBody},
+    http::{HeaderMap, Request, Response},
+};
+use http_body::Frame;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+use tower_http::trace::{OnBodyChunk, OnEos};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::dyn_event;
+
+/// An implementor of [`OnBodyChunk`] which records the lifecycle of the response body.
+///
+/// [`AxumOtelOnResponse`] runs when the response headers are ready, so for streamed
+/// responses such as server-sent events or downloads its latency is only the time to
+/// headers. This component adds the following attributes to the span:
+///
+/// - `http.response.time_to_first_byte_ms`: The time from the request to the first
+///   body chunk, or to the end of the body for bodies without chunks
+/// - `http.response.body.duration_ms`: The time from the request until the body was
+///   finished or dropped
+/// - `http.response.body.chunks`: The number of body chunks sent
+/// - `http.response.body.size`: The number of body bytes sent
+/// - `http.response.body.completed`: `true` when the whole body was sent and `false`
+///   when it was dropped early, for example because the client disconnected
+///
+/// A "response stream closed" event with the same information is emitted once the body
+/// is finished or dropped.
+///
+/// tower-http does not report the end of the body to [`OnBodyChunk`], so
+/// `http.response.body.completed` is only recorded when the component is applied with
+/// a [`ResponseBodyLayer`] inside the trace layer, which [`AxumOtelLayer`] does. As a
+/// plain [`OnBodyChunk`], only bodies with at least one chunk are recorded.
+///
+/// Each request gets its own instance, cloned from the one configured on the trace
+/// layer, so cloning resets the recorded state.
+///
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum_otel::{AxumOtelOnBodyChunk, AxumOtelOnEos, Level};
+/// use tower_http::trace::TraceLayer;
+///
+/// let layer = TraceLayer::new_for_http()
+///     .on_body_chunk(AxumOtelOnBodyChunk::new().level(Level::INFO))
+///     .on_eos(AxumOtelOnEos::new().level(Level::INFO));
+/// ```
+#[derive(Debug)]
+pub struct AxumOtelOnBodyChunk {
+    level: Level,
+    stream: Option<BodyStream>,
+}
+
+/// The state of a response body being recorded.
+#[derive(Debug)]
+struct BodyStream {
+    span: Span,
+    start: Instant,
+    end: Option<Instant>,
+    chunks: u64,
+    bytes: u64,
+    /// Whether the body was sent entirely, when its end is tracked.
+    completed: Option<bool>,
+}
+
+impl BodyStream {
+    fn new(span: Span, start: Instant, completed: Option<bool>) -> Self {
+        Self {
+            span,
+            start,
+            end: None,
+            chunks: 0,
+            bytes: 0,
+            completed,
+        }
+    }
+
+    fn record_time_to_first_byte(&self) {
+        self.span.set_attribute(
+            "http.response.time_to_first_byte_ms",
+            self.start.elapsed().as_millis() as i64,
+        );
+    }
+
+    fn record_chunk(&mut self, chunk: &Bytes) {
+        if self.chunks == 0 {
+            self.record_time_to_first_byte();
+        }
+        self.chunks += 1;
+        self.bytes += chunk.len() as u64;
+    }
+}
+
+impl Default for AxumOtelOnBodyChunk {
+    fn default() -> Self {
+        Self {
+            level: Level::DEBUG,
+            stream: None,
+        }
+    }
+}
+
+impl Clone for AxumOtelOnBodyChunk {
+    fn clone(&self) -> Self {
+        Self {
+            level: self.level,
+            stream: None,
+        }
+    }
+}
+
+impl AxumOtelOnBodyChunk {
+    /// Create a new `AxumOtelOnBodyChunk`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Level`] used for the "response stream closed" event.
+    ///
+    /// Defaults to [`Level::DEBUG`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Start recording a body whose end is tracked, from the start of the request.
+    fn track(mut self, span: Span, start: Instant) -> Self {
+        if !span.is_none() {
+            self.stream = Some(BodyStream::new(span, start, Some(false)));
+        }
+        self
+    }
+
+    /// Mark the tracked body as sent entirely.
+    fn complete(&mut self) {
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        if stream.completed != Some(false) {
+            return;
+        }
+        if stream.chunks == 0 {
+            stream.record_time_to_first_byte();
+        }
+        stream.completed = Some(true);
+        stream.end = Some(Instant::now());
+    }
+}
+
+impl OnBodyChunk<Bytes> for AxumOtelOnBodyChunk {
+    fn on_body_chunk(&mut self, chunk: &Bytes, latency: Duration, span: &Span) {
+        // The span creator skipped this request
+        if span.is_none() {
+            return;
+        }
+
+        self.stream
+            .get_or_insert_with(|| {
+                // The latency of the first chunk is measured from the start of the request
+                let start = Instant::now()
+                    .checked_sub(latency)
+                    .unwrap_or_else(Instant::now);
+                BodyStream::new(span.clone(), start, None)
+            })
+            .record_chunk(chunk);
+    }
+}
+
+impl Drop for AxumOtelOnBodyChunk {
+    fn drop(&mut self) {
+        let Some(stream) = self.stream.take() else {
+            return;
+        };
+
+        let duration = stream.end.unwrap_or_else(Instant::now) - stream.start;
+        let span = &stream.span;
+        span.set_attribute(
+            "http.response.body.duration_ms",
+            duration.as_millis() as i64,
+        );
+        span.set_attribute("http.response.body.chunks", stream.chunks as i64);
+        span.set_attribute("http.response.body.size", stream.bytes as i64);
+        if let Some(completed) = stream.completed {
+            span.set_attribute("http.response.body.completed", completed);
+        }
+        span.in_scope(|| {
+            dyn_event!(
+                self.level,
+                duration = duration.as_millis() as u64,
+                chunks = stream.chunks,
+                bytes = stream.bytes,
+                completed = stream.completed,
+                "response stream closed"
+            );
+        });
+    }
+}
+
+/// A [`Layer`] that records the response body with an [`AxumOtelOnBodyChunk`], including
+/// whether the body was sent entirely.
+///
+/// The end of the body is detected when the body reports it with
+/// [`is_end_stream`](http_body::Body::is_end_stream) or returns no more frames. Bodies
+/// dropped before that are recorded as not completed.
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of. The trace layer itself should
+/// keep its default [`OnBodyChunk`], so chunks are not counted twice.
+///
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelOnBodyChunk, AxumOtelSpanCreator, Level, ResponseBodyLayer};
+/// use tower_http::trace::TraceLayer;
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .layer(ResponseBodyLayer::new(
+///         AxumOtelOnBodyChunk::new().level(Level::INFO),
+///     ))
+///     .layer(TraceLayer::new_for_http().make_span_with(AxumOtelSpanCreator::new()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ResponseBodyLayer {
+    on_body_chunk: AxumOtelOnBodyChunk,
+}
+
+impl ResponseBodyLayer {
+    /// Create a new `ResponseBodyLayer` recording with the given [`AxumOtelOnBodyChunk`].
+    pub fn new(on_body_chunk: AxumOtelOnBodyChunk) -> Self {
+        Self { on_body_chunk }
+    }
+}
+
+impl<S> Layer<S> for ResponseBodyLayer {
+    type Service = TrackResponseBody<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TrackResponseBody {
+            inner,
+            on_body_chunk: self.on_body_chunk.clone(),
+        }
+    }
+}
+
+/// Middleware that records the response body with an [`AxumOtelOnBodyChunk`].
+///
+/// See [`ResponseBodyLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct TrackResponseBody<S> {
+    inner: S,
+    on_body_chunk: AxumOtelOnBodyChunk,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TrackResponseBody<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: HttpBody<Data = Bytes>,
+{
+    type Response = Response<TrackedBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        ResponseFuture {
+            inner: self.inner.call(request),
+            on_body_chunk: Some(self.on_body_chunk.clone()),
+            span: Span::current(),
+            start: Instant::now(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`TrackResponseBody`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        on_body_chunk: Option<AxumOtelOnBodyChunk>,
+        span: Span,
+        start: Instant,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: HttpBody<Data = Bytes>,
+{
+    type Output = Result<Response<TrackedBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let response = ready!(this.inner.poll(cx))?;
+        let on_body_chunk = this
+            .on_body_chunk
+            .take()
+            .expect("polled after completion")
+            .track(this.span.clone(), *this.start);
+
+        Poll::Ready(Ok(response.map(|inner| TrackedBody {
+            inner,
+            on_body_chunk,
+        })))
+    }
+}
+
+pin_project! {
+    /// Response body for [`TrackResponseBody`].
+    pub struct TrackedBody<B>
+    where
+        B: HttpBody,
+    {
+        #[pin]
+        inner: B,
+        on_body_chunk: AxumOtelOnBodyChunk,
+    }
+
+    impl<B> PinnedDrop for TrackedBody<B>
+    where
+        B: HttpBody,
+    {
+        fn drop(this: Pin<&mut Self>) {
+            // Bodies that know they are empty, or ended with their last chunk, may not
+            // be polled again
+            let this = this.project();
+            if this.inner.is_end_stream() {
+                this.on_body_chunk.complete();
+            }
+        }
+    }
+}
+
+impl<B> HttpBody for TrackedBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Self::Error>>> {
+        let mut this = self.project();
+        let result = ready!(this.inner.as_mut().poll_frame(cx));
+        match &result {
+            Some(Ok(frame)) => {
+                if let (Some(chunk), Some(stream)) =
+                    (frame.data_ref(), &mut this.on_body_chunk.stream)
+                {
+                    stream.record_chunk(chunk);
+                }
+                if this.inner.is_end_stream() {
+                    this.on_body_chunk.complete();
+                }
+            }
+            Some(Err(_)) => {}
+            None => this.on_body_chunk.complete(),
+        }
+        Poll::Ready(result)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// An implementor of [`OnEos`] which records the end of a response stream.
+///
+/// This component adds the `http.response.stream.duration_ms` attribute, the time from
+/// the response headers to the end of the stream, and emits a "response stream
+/// finished" event.
+///
+/// tower-http only calls [`OnEos`] for classifiers that inspect the end of the stream,
+/// such as the gRPC classifier. With the HTTP classifier, the "response stream closed"
+/// event of [`AxumOtelOnBodyChunk`] and its `completed` field mark the end of the stream
+/// instead.
+#[derive(Clone, Copy, Debug)]
+pub struct AxumOtelOnEos {
+    level: Level,
+}
+
+impl Default for AxumOtelOnEos {
+    fn default() -> Self {
+        Self {
+            level: Level::DEBUG,
+        }
+    }
+}
+
+impl AxumOtelOnEos {
+    /// Create a new `AxumOtelOnEos`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Level`] used for the "response stream finished" event.
+    ///
+    /// Defaults to [`Level::DEBUG`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl OnEos for AxumOtelOnEos {
+    fn on_eos(self, _trailers: Option<&HeaderMap>, stream_duration: Duration, span: &Span) {
+        // The span creator skipped this request
+        if span.is_none() {
+            return;
+        }
+
+        span.set_attribute(
+            "http.response.stream.duration_ms",
+            stream_duration.as_millis() as i64,
+        );
+        dyn_event!(
+            self.level,
+            stream_duration = stream_duration.as_millis() as u64,
+            "response stream finished"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelRouterExt, AxumOtelSpanCreator,
+    };
+    use axum::{body::Body, routing::get, Router};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::SpanData;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+    use tower_http::trace::TraceLayer;
+
+    /// A body that yields one chunk and then never ends.
+    struct EndlessBody(Option<Bytes>);
+
+    impl HttpBody for EndlessBody {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+            match self.0.take() {
+                Some(chunk) => Poll::Ready(Some(Ok(Frame::data(chunk)))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async { "hello" }))
+            .route("/empty", get(|| async {}))
+            .route(
+                "/endless",
+                get(|| async { Body::new(EndlessBody(Some(Bytes::from_static(b"hello")))) }),
+            )
+    }
+
+    async fn send(app: Router, uri: &str) -> Body {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().into_body()
+    }
+
+    fn assert_closed(span: &SpanData, chunks: i64, completed: Option<bool>) {
+        assert_eq!(
+            attribute(span, "http.response.body.chunks"),
+            Some(chunks.into())
+        );
+        assert_eq!(
+            attribute(span, "http.response.body.completed"),
+            completed.map(Value::Bool)
+        );
+        assert!(attribute(span, "http.response.time_to_first_byte_ms").is_some());
+        assert!(span
+            .events
+            .iter()
+            .any(|event| event.name == "response stream closed"));
+    }
+
+    #[tokio::test]
+    async fn test_completed_body_is_recorded() {
+        let collector = SpanCollector::install();
+        let body = send(app().with_otel(), "/").await;
+        axum::body::to_bytes(body, usize::MAX).await.unwrap();
+
+        let span = &collector.spans()[0];
+        assert_closed(span, 1, Some(true));
+        assert_eq!(attribute(span, "http.response.body.size"), Some(5.into()));
+    }
+
+    #[tokio::test]
+    async fn test_empty_body_is_completed_without_polling() {
+        let collector = SpanCollector::install();
+        drop(send(app().with_otel(), "/empty").await);
+
+        assert_closed(&collector.spans()[0], 0, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_body_is_not_completed() {
+        let collector = SpanCollector::install();
+        let mut body = send(app().with_otel(), "/endless").await;
+        let frame = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await;
+        assert!(frame.is_some());
+        drop(body);
+
+        assert_closed(&collector.spans()[0], 1, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_on_body_chunk_without_tracking() {
+        let collector = SpanCollector::install();
+        let app = app().layer(
+            TraceLayer::new_for_http()
+                .make_span_with(AxumOtelSpanCreator::new())
+                .on_body_chunk(AxumOtelOnBodyChunk::new()),
+        );
+        let body = send(app, "/").await;
+        axum::body::to_bytes(body, usize::MAX).await.unwrap();
+
+        assert_closed(&collector.spans()[0], 1, None);
+    }
+}
@@ -1,12 +1,37 @@
-use tower_http::{classify::ServerErrorsFailureClass, trace::OnFailure};
+use crate::{StatusLevels, StatusPolicy};
+use opentelemetry::KeyValue;
+use tower_http::{
+    classify::{GrpcFailureClass, ServerErrorsFailureClass},
+    trace::OnFailure,
+};
 use tracing::Level;
-use tracing_otel_extra::dyn_event;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::{dyn_event, extract::schema};
+
+/// gRPC status codes that mark a server span as failed, following the semantic conventions.
+///
+/// These are `UNKNOWN`, `DEADLINE_EXCEEDED`, `UNIMPLEMENTED`, `INTERNAL`, `UNAVAILABLE`
+/// and `DATA_LOSS`.
+const GRPC_SERVER_ERROR_CODES: [i32; 6] = [2, 4, 12, 13, 14, 15];
 
 /// An implementor of [`OnFailure`] which records the failure status code.
 ///
 /// Original implementation from [tower-http](https://github.com/tower-rs/tower-http/blob/main/tower-http/src/trace/on_failure.rs).
 ///
-/// This component updates the span's `otel.status_code` to "ERROR" when a server error occurs.
+/// This component updates the span when a request fails:
+///
+/// - Failed status codes set `otel.status_code` according to the [`StatusPolicy`],
+///   which marks `5xx` responses as "ERROR" by default
+/// - Errors set `otel.status_code` to "ERROR", record the error as
+///   `otel.status_message` and add an `exception` event to the span
+///
+/// Both the HTTP classifier ([`ServerErrorsAsFailures`]) and the gRPC classifier
+/// ([`GrpcErrorsAsFailures`]) are supported. gRPC status codes are recorded as
+/// `rpc.grpc.status_code`, and mark the span as "ERROR" for the codes the semantic
+/// conventions consider server errors.
+///
+/// [`ServerErrorsAsFailures`]: tower_http::classify::ServerErrorsAsFailures
+/// [`GrpcErrorsAsFailures`]: tower_http::classify::GrpcErrorsAsFailures
 ///
 /// # Example
 ///
@@ -16,16 +41,20 @@ use tracing_otel_extra::dyn_event;
 ///
 /// let layer = TraceLayer::new_for_http()
 ///     .on_failure(AxumOtelOnFailure::new().level(Level::INFO));
+///
+/// let grpc_layer = TraceLayer::new_for_grpc().on_failure(AxumOtelOnFailure::new());
 /// ```
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct AxumOtelOnFailure {
-    level: Level,
+    levels: StatusLevels,
+    status_policy: StatusPolicy,
 }
 
 impl Default for AxumOtelOnFailure {
     fn default() -> Self {
         Self {
-            level: Level::ERROR,
+            levels: StatusLevels::uniform(Level::ERROR),
+            status_policy: StatusPolicy::default(),
         }
     }
 }
@@ -42,7 +71,26 @@ impl AxumOtelOnFailure {
     ///
     /// [tracing events]: https://docs.rs/tracing/latest/tracing/#events
     pub fn level(mut self, level: Level) -> Self {
-        self.level = level;
+        self.levels = StatusLevels::uniform(level);
+        self
+    }
+
+    /// Set the [`StatusLevels`] used to pick the event level from the failed status.
+    ///
+    /// Failures without a status code, such as errors and gRPC failures, use the `5xx`
+    /// level. This replaces the level set with [`AxumOtelOnFailure::level`].
+    pub fn status_levels(mut self, levels: StatusLevels) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Set the [`StatusPolicy`] used to map failed status codes to the span status.
+    ///
+    /// This should match the policy of the [`AxumOtelOnResponse`].
+    ///
+    /// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+    pub fn status_policy(mut self, status_policy: StatusPolicy) -> Self {
+        self.status_policy = status_policy;
         self
     }
 }
@@ -54,17 +102,66 @@ impl OnFailure<ServerErrorsFailureClass> for AxumOtelOnFailure {
         latency: std::time::Duration,
         span: &tracing::Span,
     ) {
+        // The span creator skipped this request
+        if span.is_none() {
+            return;
+        }
+
+        let level = match &failure_classification {
+            ServerErrorsFailureClass::StatusCode(status) => self.levels.level_for(*status),
+            ServerErrorsFailureClass::Error(_) => self.levels.error_level(),
+        };
+        dyn_event!(
+            level,
+            classification = %failure_classification,
+            latency = latency.as_millis() as u64,
+            "response failed"
+        );
+        match failure_classification {
+            ServerErrorsFailureClass::StatusCode(status) => {
+                if let Some(status_code) = self.status_policy.classify(status).as_otel_status_code()
+                {
+                    span.record(schema::OTEL_STATUS_CODE, status_code);
+                }
+            }
+            ServerErrorsFailureClass::Error(error) => record_error(span, error),
+        }
+    }
+}
+
+impl OnFailure<GrpcFailureClass> for AxumOtelOnFailure {
+    fn on_failure(
+        &mut self,
+        failure_classification: GrpcFailureClass,
+        latency: std::time::Duration,
+        span: &tracing::Span,
+    ) {
+        // The span creator skipped this request
+        if span.is_none() {
+            return;
+        }
+
         dyn_event!(
-            self.level,
+            self.levels.error_level(),
             classification = %failure_classification,
-            latency = %latency.as_millis(),
+            latency = latency.as_millis() as u64,
             "response failed"
         );
         match failure_classification {
-            ServerErrorsFailureClass::StatusCode(status) if status.is_server_error() => {
-                span.record("otel.status_code", "ERROR");
+            GrpcFailureClass::Code(code) => {
+                span.set_attribute("rpc.grpc.status_code", i64::from(code.get()));
+                if GRPC_SERVER_ERROR_CODES.contains(&code.get()) {
+                    span.record(schema::OTEL_STATUS_CODE, "ERROR");
+                }
             }
-            _ => {}
+            GrpcFailureClass::Error(error) => record_error(span, error),
         }
     }
 }
+
+/// Mark the span as failed with the error message and add an `exception` event.
+fn record_error(span: &tracing::Span, error: String) {
+    span.record(schema::OTEL_STATUS_CODE, "ERROR");
+    span.record(schema::OTEL_STATUS_MESSAGE, error.as_str());
+    span.add_event("exception", vec![KeyValue::new("exception.message", error)]);
+}
@@ -1,7 +1,14 @@
-use axum::http;
+use crate::{
+    enrich::{ResponseEnrichers, SpanAttributes},
+    headers::{HeaderCapture, RESPONSE_HEADER_PREFIX},
+    ErrorReport, HttpSemConv, MiddlewareRejection, ResponseInfo, SlowRequests, SpanStatus,
+    StatusLevels, StatusPolicy,
+};
+use axum::http::{self, header::CONTENT_TYPE, HeaderMap};
+use std::sync::Arc;
 use tower_http::trace::OnResponse;
 use tracing::Level;
-use tracing_otel_extra::dyn_event;
+use tracing_otel_extra::{dyn_event, extract::schema};
 
 /// An implementor of [`OnResponse`] which records the response status code and latency.
 ///
@@ -9,8 +16,21 @@ use tracing_otel_extra::dyn_event;
 ///
 /// This component adds the following attributes to the span:
 ///
-/// - `http.status_code`: The response status code
-/// - `otel.status_code`: The OpenTelemetry status code (OK for successful responses)
+/// - `http.status_code`: The response status code, or `http.response.status_code` with the
+///   stable [`HttpSemConv`]
+/// - `otel.status_code`: The OpenTelemetry status code according to the [`StatusPolicy`],
+///   which is "OK" for successful responses and "ERROR" for `5xx` responses by default.
+///   gRPC responses, with an `application/grpc` content type, are left unset since their
+///   HTTP status is `200` even for failed calls
+/// - `http.response.header.<name>`: The response headers selected with
+///   [`AxumOtelOnResponse::response_headers`]
+///
+/// Extra attributes can be recorded with [`AxumOtelOnResponse::enrich`].
+///
+/// When the response extensions contain an [`ErrorReport`], an `exception` event is
+/// added to the span as well. A [`MiddlewareRejection`] records `error.type` and a
+/// `request rejected` event. Requests slower than the thresholds set with
+/// [`AxumOtelOnResponse::slow_requests`] get a "slow request" event.
 ///
 /// # Example
 ///
@@ -21,15 +41,25 @@ use tracing_otel_extra::dyn_event;
 /// let layer = TraceLayer::new_for_http()
 ///     .on_response(AxumOtelOnResponse::new().level(Level::INFO));
 /// ```
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct AxumOtelOnResponse {
-    level: Level,
+    levels: StatusLevels,
+    semconv: HttpSemConv,
+    response_headers: Option<HeaderCapture>,
+    status_policy: StatusPolicy,
+    enrichers: ResponseEnrichers,
+    slow_requests: Option<SlowRequests>,
 }
 
 impl Default for AxumOtelOnResponse {
     fn default() -> Self {
         Self {
-            level: Level::DEBUG,
+            levels: StatusLevels::uniform(Level::DEBUG),
+            semconv: HttpSemConv::Legacy,
+            response_headers: None,
+            status_policy: StatusPolicy::default(),
+            enrichers: ResponseEnrichers::default(),
+            slow_requests: None,
         }
     }
 }
@@ -52,7 +82,83 @@ impl AxumOtelOnResponse {
     /// [tracing events]: https://docs.rs/tracing/latest/tracing/#events
     /// [`AxumOtelOnResponse::level`]: crate::make_span::AxumOtelSpanCreator::level
     pub fn level(mut self, level: Level) -> Self {
-        self.level = level;
+        self.levels = StatusLevels::uniform(level);
+        self
+    }
+
+    /// Set the [`StatusLevels`] used to pick the event level from the response status.
+    ///
+    /// This replaces the level set with [`AxumOtelOnResponse::level`].
+    pub fn status_levels(mut self, levels: StatusLevels) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Set the [`HttpSemConv`] used for the span attributes.
+    ///
+    /// This should match the conventions of the [`AxumOtelSpanCreator`].
+    ///
+    /// Defaults to [`HttpSemConv::Legacy`].
+    ///
+    /// [`AxumOtelSpanCreator`]: crate::AxumOtelSpanCreator
+    pub fn semconv(mut self, semconv: HttpSemConv) -> Self {
+        self.semconv = semconv;
+        self
+    }
+
+    /// Record the response headers in the [`HeaderCapture`] allowlist as
+    /// `http.response.header.<name>` attributes.
+    ///
+    /// By default no response headers are captured.
+    pub fn response_headers(mut self, headers: HeaderCapture) -> Self {
+        self.response_headers = Some(headers);
+        self
+    }
+
+    /// Set the [`StatusPolicy`] used to map the response status code to the span status.
+    ///
+    /// Defaults to "OK" for successful responses and "ERROR" for `5xx` responses.
+    pub fn status_policy(mut self, status_policy: StatusPolicy) -> Self {
+        self.status_policy = status_policy;
+        self
+    }
+
+    /// Record extra attributes on the request span with the given callback.
+    ///
+    /// The callback runs when the response is ready, and can read the response
+    /// extensions inserted by handlers. See [`AxumOtelSpanCreator::enrich`] for the
+    /// attributes that are recorded as span fields.
+    ///
+    /// [`AxumOtelSpanCreator::enrich`]: crate::AxumOtelSpanCreator::enrich
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use axum_otel::AxumOtelOnResponse;
+    ///
+    /// #[derive(Clone)]
+    /// struct Variant(&'static str);
+    ///
+    /// let on_response = AxumOtelOnResponse::new().enrich(|response, attributes| {
+    ///     if let Some(Variant(variant)) = response.extensions().get::<Variant>() {
+    ///         attributes.record("feature_flag.variant", *variant);
+    ///     }
+    /// });
+    /// ```
+    pub fn enrich<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ResponseInfo<'_>, &mut SpanAttributes<'_>) + Send + Sync + 'static,
+    {
+        self.enrichers.push(Arc::new(callback));
+        self
+    }
+
+    /// Report requests slower than the [`SlowRequests`] thresholds with a separate
+    /// "slow request" event.
+    ///
+    /// By default no requests are reported as slow.
+    pub fn slow_requests(mut self, slow_requests: SlowRequests) -> Self {
+        self.slow_requests = Some(slow_requests);
         self
     }
 }
@@ -64,15 +170,54 @@ impl<B> OnResponse<B> for AxumOtelOnResponse {
         latency: std::time::Duration,
         span: &tracing::Span,
     ) {
+        // The span creator skipped this request
+        if span.is_none() {
+            return;
+        }
+
         let status = response.status().as_u16();
-        span.record("http.status_code", tracing::field::display(status));
-        span.record("otel.status_code", "OK");
+        // gRPC failures are classified from the `grpc-status`, by the gRPC classifier
+        let span_status = if is_grpc(response.headers()) {
+            SpanStatus::Unset
+        } else {
+            self.status_policy.classify(response.status())
+        };
+        if self.semconv.emit_legacy() {
+            span.record(schema::HTTP_STATUS_CODE, i64::from(status));
+        }
+        if self.semconv.emit_stable() {
+            span.record(schema::HTTP_RESPONSE_STATUS_CODE, i64::from(status));
+        }
+        if let Some(status_code) = span_status.as_otel_status_code() {
+            span.record(schema::OTEL_STATUS_CODE, status_code);
+        }
+        if let Some(headers) = &self.response_headers {
+            headers.record(RESPONSE_HEADER_PREFIX, response.headers(), span);
+        }
+        if let Some(report) = response.extensions().get::<ErrorReport>() {
+            report.record(span);
+        }
+        if let Some(rejection) = response.extensions().get::<MiddlewareRejection>() {
+            rejection.record(span);
+        }
+        let response_info = ResponseInfo::new(response);
+        self.enrichers.enrich(&response_info, span);
+        if let Some(slow_requests) = &self.slow_requests {
+            slow_requests.record(&response_info, latency, span);
+        }
 
         dyn_event!(
-            self.level,
-            latency = %latency.as_millis(),
-            status = %status,
+            self.levels.level_for(response.status()),
+            latency = latency.as_millis() as u64,
+            status,
             "finished processing request"
         );
     }
 }
+
+/// Whether the response is a gRPC response, from its content type.
+fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/grpc"))
+}
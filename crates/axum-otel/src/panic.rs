@@ -0,0 +1,320 @@
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    http::{Request, Response, StatusCode},
+    BoxError,
+};
+use opentelemetry::KeyValue;
+use pin_project_lite::pin_project;
+use std::{
+    any::Any,
+    cell::RefCell,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::Once,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::extract::schema;
+
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    /// The location of the last panic on this thread, recorded by the panic hook.
+    static PANIC_LOCATION: RefCell<Option<PanicLocation>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Debug)]
+struct PanicLocation {
+    file: String,
+    line: u32,
+    column: u32,
+}
+
+/// Install a panic hook that records the location of panics for [`CatchPanicLayer`].
+///
+/// Without the hook, panics are still recorded on the request span, only without their
+/// location. The hook delegates to the previously installed hook, so the panic message
+/// is still printed, and installing it more than once has no effect. Call this after
+/// installing your own panic hook; hooks installed afterwards must call the previous
+/// hook to keep the location.
+///
+/// # Example
+///
+/// ```rust
+/// axum_otel::install_panic_hook();
+/// ```
+pub fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Some(location) = info.location() {
+                let location = PanicLocation {
+                    file: location.file().to_owned(),
+                    line: location.line(),
+                    column: location.column(),
+                };
+                PANIC_LOCATION.with(|cell| *cell.borrow_mut() = Some(location));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Run `f`, catching panics, with the location of an earlier panic on this thread
+/// cleared so it is not mistaken for the location of a panic in `f`.
+fn catch_unwind<R>(f: impl FnOnce() -> R) -> std::thread::Result<R> {
+    if INSTALL_PANIC_HOOK.is_completed() {
+        PANIC_LOCATION.with(|cell| cell.borrow_mut().take());
+    }
+    panic::catch_unwind(AssertUnwindSafe(f))
+}
+
+/// A [`Layer`] that turns panics in handlers into `500 Internal Server Error` responses
+/// and records them on the request span.
+///
+/// Unlike tower-http's `CatchPanicLayer`, the panic is recorded as an `exception` event
+/// on the active request span, with the following attributes, and the span status is
+/// set to "ERROR":
+///
+/// - `exception.type`: `panic`
+/// - `exception.message`: The panic payload, when it is a string
+/// - `exception.escaped`: `true`
+/// - `code.file.path`, `code.line.number` and `code.column.number`: The panic location
+///
+/// The location is only recorded once the panic hook is installed with
+/// [`install_panic_hook`], the layer never installs it on its own.
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{install_panic_hook, AxumOtelLayer};
+///
+/// async fn handler() -> &'static str {
+///     panic!("something went wrong")
+/// }
+///
+/// // Optional, to record the location of panics
+/// install_panic_hook();
+///
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .layer(AxumOtelLayer::new().catch_panic(true));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CatchPanicLayer {
+    _priv: (),
+}
+
+impl CatchPanicLayer {
+    /// Create a new `CatchPanicLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic { inner }
+    }
+}
+
+/// Middleware that turns panics into `500 Internal Server Error` responses and records
+/// them on the request span.
+///
+/// See [`CatchPanicLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CatchPanic<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let span = Span::current();
+        match catch_unwind(|| self.inner.call(request)) {
+            Ok(future) => ResponseFuture {
+                inner: Some(future),
+                span,
+            },
+            Err(payload) => {
+                record_panic(&span, payload.as_ref());
+                ResponseFuture { inner: None, span }
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`CatchPanic`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: Option<F>,
+        span: Span,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Output = Result<Response<Body>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let Some(inner) = this.inner.as_pin_mut() else {
+            // The inner service panicked in `call`
+            return Poll::Ready(Ok(panic_response()));
+        };
+        match catch_unwind(|| inner.poll(cx)) {
+            Ok(Poll::Ready(result)) => Poll::Ready(result.map(|response| response.map(Body::new))),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => {
+                record_panic(this.span, payload.as_ref());
+                Poll::Ready(Ok(panic_response()))
+            }
+        }
+    }
+}
+
+fn panic_response() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    response
+}
+
+/// Returns the panic payload if it is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
+
+/// Add the `exception` event for the panic and mark the span as failed.
+fn record_panic(span: &Span, payload: &(dyn Any + Send)) {
+    let location = PANIC_LOCATION.with(|cell| cell.borrow_mut().take());
+    if span.is_none() {
+        return;
+    }
+
+    let message = panic_message(payload).unwrap_or("Box<dyn Any>");
+    let mut attributes = vec![
+        KeyValue::new("exception.type", "panic"),
+        KeyValue::new("exception.message", message.to_owned()),
+        KeyValue::new("exception.escaped", true),
+    ];
+    if let Some(location) = location {
+        attributes.push(KeyValue::new("code.file.path", location.file));
+        attributes.push(KeyValue::new("code.line.number", i64::from(location.line)));
+        attributes.push(KeyValue::new(
+            "code.column.number",
+            i64::from(location.column),
+        ));
+    }
+    span.add_event("exception", attributes);
+    // No `otel.status_message`: recording `otel.status_code` for the `500` response
+    // afterwards resets it, the message is on the `exception` event instead
+    span.record(schema::OTEL_STATUS_CODE, "ERROR");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelLayer,
+    };
+    use axum::{routing::get, Router};
+    use opentelemetry::{trace::Status, Value};
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn test_catch_panic_responds_with_500() {
+        let service =
+            CatchPanicLayer::new().layer(service_fn(|request: Request<Body>| async move {
+                if request.uri().path() == "/panic" {
+                    panic!("boom");
+                }
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            }));
+
+        let request = Request::get("/panic").body(Body::empty()).unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_panic_is_recorded_on_the_request_span() {
+        async fn handler() -> &'static str {
+            panic!("boom")
+        }
+
+        install_panic_hook();
+        let collector = SpanCollector::install();
+        let app = Router::new()
+            .route("/panic", get(handler))
+            .layer(AxumOtelLayer::new().catch_panic(true));
+
+        let request = Request::get("/panic").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        drop(response);
+
+        let spans = collector.spans();
+        let span = &spans[0];
+        assert_eq!(attribute(span, "http.route"), Some("/panic".into()));
+        assert!(matches!(span.status, Status::Error { .. }));
+
+        let event = span
+            .events
+            .iter()
+            .find(|event| event.name == "exception")
+            .unwrap();
+        let event_attribute = |key: &str| {
+            event
+                .attributes
+                .iter()
+                .find(|attribute| attribute.key.as_str() == key)
+                .map(|attribute| attribute.value.clone())
+        };
+        assert_eq!(event_attribute("exception.type"), Some("panic".into()));
+        assert_eq!(event_attribute("exception.message"), Some("boom".into()));
+        assert_eq!(
+            event_attribute("exception.escaped"),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(event_attribute("code.file.path"), Some(file!().into()));
+        assert!(event_attribute("code.line.number").is_some());
+    }
+}
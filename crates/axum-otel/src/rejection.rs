@@ -0,0 +1,314 @@
+use crate::ErrorReport;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
+    BoxError,
+};
+use opentelemetry::KeyValue;
+use std::{convert::Infallible, error::Error, fmt};
+use tower::{load_shed::error::Overloaded, timeout::error::Elapsed};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// A request rejected by a middleware because of capacity limits, rather than failed
+/// by the handler.
+///
+/// Insert the rejection into the response extensions, usually by returning it from a
+/// [`HandleErrorLayer`] function, and [`AxumOtelOnResponse`] records the following
+/// on the request span:
+///
+/// - `error.type`: `timeout`, `overloaded` or `rate_limited`
+/// - A `request rejected` event with the same `error.type`
+///
+/// [`HttpMetricsLayer`] uses the same `error.type` for the request duration, so
+/// capacity problems can be told apart from handler errors.
+///
+/// [`MiddlewareRejection::handle_error`] recognizes the errors of tower's
+/// `Timeout` and `LoadShed` middleware. `LoadShed` does not tell which limit was hit,
+/// so use [`MiddlewareRejection::handle_rate_limit_error`] instead when it wraps a
+/// `RateLimit`, to record `rate_limited`.
+///
+/// [`HandleErrorLayer`]: axum::error_handling::HandleErrorLayer
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+/// [`HttpMetricsLayer`]: crate::HttpMetricsLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{error_handling::HandleErrorLayer, routing::get, Router};
+/// use axum_otel::{AxumOtelRouterExt, MiddlewareRejection};
+/// use std::time::Duration;
+/// use tower::ServiceBuilder;
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .layer(
+///         ServiceBuilder::new()
+///             .layer(HandleErrorLayer::new(MiddlewareRejection::handle_error))
+///             .timeout(Duration::from_secs(10)),
+///     )
+///     .with_otel();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MiddlewareRejection {
+    /// The request took longer than the configured timeout.
+    Timeout,
+    /// The service was at capacity and shed the request.
+    Overloaded,
+    /// The client exceeded its rate limit, see
+    /// [`MiddlewareRejection::handle_rate_limit_error`].
+    RateLimited,
+}
+
+impl MiddlewareRejection {
+    /// Recognize the rejection from a middleware error or its [`Error::source`] chain.
+    ///
+    /// Returns `None` for errors that are not capacity rejections.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Option<Self> {
+        let mut error = Some(error);
+        while let Some(cause) = error {
+            if cause.is::<Elapsed>() {
+                return Some(Self::Timeout);
+            }
+            if cause.is::<Overloaded>() {
+                return Some(Self::Overloaded);
+            }
+            error = cause.source();
+        }
+        None
+    }
+
+    /// Convert a middleware error into a response, for use with [`HandleErrorLayer`].
+    ///
+    /// Rejections respond with their [`status_code`](Self::status_code), other errors
+    /// respond with `500 Internal Server Error` and an [`ErrorReport`].
+    ///
+    /// [`HandleErrorLayer`]: axum::error_handling::HandleErrorLayer
+    pub async fn handle_error(error: BoxError) -> Response {
+        match Self::from_error(error.as_ref()) {
+            Some(rejection) => rejection.into_response(),
+            None => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorReport::new(error.as_ref()),
+                "internal server error",
+            )
+                .into_response(),
+        }
+    }
+
+    /// Like [`handle_error`](Self::handle_error), for a `LoadShed` that wraps a
+    /// `RateLimit`, so shed requests are recorded as [`RateLimited`](Self::RateLimited).
+    ///
+    /// `RateLimit` itself never fails, it holds requests back until the rate allows
+    /// them. With `LoadShed` in front, requests over the rate are shed instead.
+    ///
+    /// ```rust,no_run
+    /// use axum::{error_handling::HandleErrorLayer, routing::get, Router};
+    /// use axum_otel::{AxumOtelRouterExt, MiddlewareRejection};
+    /// use std::time::Duration;
+    /// use tower::ServiceBuilder;
+    ///
+    /// async fn handler() -> &'static str {
+    ///     "Hello, world!"
+    /// }
+    ///
+    /// let app: Router<()> = Router::new()
+    ///     .route("/", get(handler))
+    ///     .layer(
+    ///         ServiceBuilder::new()
+    ///             .layer(HandleErrorLayer::new(MiddlewareRejection::handle_rate_limit_error))
+    ///             .buffer(1024)
+    ///             .load_shed()
+    ///             .rate_limit(100, Duration::from_secs(1)),
+    ///     )
+    ///     .with_otel();
+    /// ```
+    pub async fn handle_rate_limit_error(error: BoxError) -> Response {
+        match Self::from_error(error.as_ref()) {
+            Some(Self::Overloaded) => Self::RateLimited.into_response(),
+            _ => Self::handle_error(error).await,
+        }
+    }
+
+    /// The `error.type` recorded for the rejection.
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            Self::Timeout => "timeout",
+            Self::Overloaded => "overloaded",
+            Self::RateLimited => "rate_limited",
+        }
+    }
+
+    /// The status code of the rejection response.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::Timeout => StatusCode::REQUEST_TIMEOUT,
+            Self::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// Record `error.type` and the `request rejected` event on the span.
+    pub(crate) fn record(&self, span: &tracing::Span) {
+        span.set_attribute("error.type", self.error_type());
+        span.add_event(
+            "request rejected",
+            vec![KeyValue::new("error.type", self.error_type())],
+        );
+    }
+}
+
+impl fmt::Display for MiddlewareRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "request timed out"),
+            Self::Overloaded => write!(f, "service overloaded"),
+            Self::RateLimited => write!(f, "rate limit exceeded"),
+        }
+    }
+}
+
+impl IntoResponseParts for MiddlewareRejection {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.extensions_mut().insert(self);
+        Ok(res)
+    }
+}
+
+impl IntoResponse for MiddlewareRejection {
+    fn into_response(self) -> Response {
+        (self.status_code(), self, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelRouterExt,
+    };
+    use axum::{body::Body, error_handling::HandleErrorLayer, http::Request, routing::get, Router};
+    use std::{
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use tower::{Service, ServiceBuilder, ServiceExt};
+
+    /// A service that is never ready, so `LoadShed` rejects every request.
+    #[derive(Clone)]
+    struct NeverReady;
+
+    impl Service<Request<Body>> for NeverReady {
+        type Response = Response;
+        type Error = Infallible;
+        type Future = std::future::Ready<Result<Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+
+        fn call(&mut self, _request: Request<Body>) -> Self::Future {
+            unreachable!("never ready")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_error() {
+        let response = MiddlewareRejection::handle_error(Elapsed::new().into()).await;
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(
+            response.extensions().get::<MiddlewareRejection>(),
+            Some(&MiddlewareRejection::Timeout)
+        );
+
+        let response = MiddlewareRejection::handle_error(Overloaded::new().into()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = MiddlewareRejection::handle_error("connection reset".into()).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.extensions().get::<MiddlewareRejection>().is_none());
+        let report = response.extensions().get::<ErrorReport>().unwrap();
+        assert_eq!(report.error_type(), "_OTHER");
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_recorded_on_the_request_span() {
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            "too late"
+        }
+
+        let collector = SpanCollector::install();
+        let overloaded = ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(MiddlewareRejection::handle_error))
+            .load_shed()
+            .service(NeverReady);
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(slow).layer(
+                    ServiceBuilder::new()
+                        .layer(HandleErrorLayer::new(MiddlewareRejection::handle_error))
+                        .timeout(Duration::from_millis(10)),
+                ),
+            )
+            .route_service("/overloaded", overloaded)
+            .with_otel();
+
+        for (uri, status) in [
+            ("/slow", StatusCode::REQUEST_TIMEOUT),
+            ("/overloaded", StatusCode::SERVICE_UNAVAILABLE),
+        ] {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status);
+        }
+
+        let spans = collector.spans();
+        for (span, error_type) in spans.iter().zip(["timeout", "overloaded"]) {
+            assert_eq!(attribute(span, "error.type"), Some(error_type.into()));
+            let event = span
+                .events
+                .iter()
+                .find(|event| event.name == "request rejected")
+                .unwrap();
+            assert_eq!(event.attributes, [KeyValue::new("error.type", error_type)]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_requests_are_recorded() {
+        let collector = SpanCollector::install();
+        let limited = ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(
+                MiddlewareRejection::handle_rate_limit_error,
+            ))
+            .buffer(8)
+            .load_shed()
+            .rate_limit(1, Duration::from_secs(60))
+            .service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::empty()))
+            });
+        let app = Router::new().route_service("/limited", limited).with_otel();
+
+        for status in [StatusCode::OK, StatusCode::TOO_MANY_REQUESTS] {
+            let request = Request::get("/limited").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status);
+        }
+
+        let spans = collector.spans();
+        assert_eq!(attribute(&spans[0], "error.type"), None);
+        assert_eq!(
+            attribute(&spans[1], "error.type"),
+            Some("rate_limited".into())
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use axum::{
+    extract::MatchedPath,
+    http::{self, Extensions, HeaderMap, Method, StatusCode, Uri, Version},
+};
+
+/// A borrowed view of an incoming request, passed to user supplied callbacks.
+///
+/// The view is independent of the request body type, so callbacks can be stored
+/// as trait objects.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestInfo<'a> {
+    method: &'a Method,
+    uri: &'a Uri,
+    version: Version,
+    headers: &'a HeaderMap,
+    extensions: &'a Extensions,
+}
+
+impl<'a> RequestInfo<'a> {
+    /// Create a new `RequestInfo` from the given request.
+    pub fn new<B>(request: &'a http::Request<B>) -> Self {
+        Self {
+            method: request.method(),
+            uri: request.uri(),
+            version: request.version(),
+            headers: request.headers(),
+            extensions: request.extensions(),
+        }
+    }
+
+    /// The request method.
+    pub fn method(&self) -> &'a Method {
+        self.method
+    }
+
+    /// The request uri.
+    pub fn uri(&self) -> &'a Uri {
+        self.uri
+    }
+
+    /// The raw request path.
+    pub fn path(&self) -> &'a str {
+        self.uri.path()
+    }
+
+    /// The request version.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The request headers.
+    pub fn headers(&self) -> &'a HeaderMap {
+        self.headers
+    }
+
+    /// The request extensions.
+    pub fn extensions(&self) -> &'a Extensions {
+        self.extensions
+    }
+
+    /// The route the request matched, if the router has matched one yet.
+    pub fn matched_path(&self) -> Option<&'a str> {
+        self.extensions.get::<MatchedPath>().map(|p| p.as_str())
+    }
+}
+
+/// A borrowed view of an outgoing response, passed to user supplied callbacks.
+///
+/// The view is independent of the response body type, so callbacks can be stored
+/// as trait objects.
+#[derive(Clone, Copy, Debug)]
+pub struct ResponseInfo<'a> {
+    status: StatusCode,
+    version: Version,
+    headers: &'a HeaderMap,
+    extensions: &'a Extensions,
+}
+
+impl<'a> ResponseInfo<'a> {
+    /// Create a new `ResponseInfo` from the given response.
+    pub fn new<B>(response: &'a http::Response<B>) -> Self {
+        Self {
+            status: response.status(),
+            version: response.version(),
+            headers: response.headers(),
+            extensions: response.extensions(),
+        }
+    }
+
+    /// The response status code.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The response version.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// The response headers.
+    pub fn headers(&self) -> &'a HeaderMap {
+        self.headers
+    }
+
+    /// The response extensions.
+    pub fn extensions(&self) -> &'a Extensions {
+        self.extensions
+    }
+
+    /// The route the request matched, copied from the request by [`OtelContextLayer`].
+    ///
+    /// [`OtelContextLayer`]: crate::OtelContextLayer
+    pub fn matched_path(&self) -> Option<&'a str> {
+        self.extensions.get::<MatchedPath>().map(|p| p.as_str())
+    }
+}
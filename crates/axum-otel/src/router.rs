@@ -0,0 +1,46 @@
+use crate::AxumOtelLayer;
+use axum::Router;
+
+/// Extension trait for [`Router`] to add the axum-otel middleware stack.
+///
+/// Like [`Router::layer`], the middleware only applies to routes added before the
+/// call, so it should be the last step when building the router.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelLayer, AxumOtelRouterExt, Level};
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// // With the default configuration
+/// let app: Router<()> = Router::new().route("/", get(handler)).with_otel();
+///
+/// // With a custom configuration
+/// let app: Router<()> = Router::new()
+///     .route("/", get(handler))
+///     .with_otel_layer(AxumOtelLayer::new().span_level(Level::INFO));
+/// ```
+pub trait AxumOtelRouterExt {
+    /// Add the axum-otel middleware stack with the default configuration.
+    fn with_otel(self) -> Self;
+
+    /// Add the axum-otel middleware stack with the given configuration.
+    fn with_otel_layer(self, layer: AxumOtelLayer) -> Self;
+}
+
+impl<S> AxumOtelRouterExt for Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fn with_otel(self) -> Self {
+        self.with_otel_layer(AxumOtelLayer::new())
+    }
+
+    fn with_otel_layer(self, layer: AxumOtelLayer) -> Self {
+        self.layer(layer)
+    }
+}
@@ -0,0 +1,196 @@
+/// The environment variable used by OpenTelemetry SDKs to opt in to stable semantic conventions.
+const SEMCONV_STABILITY_OPT_IN: &str = "OTEL_SEMCONV_STABILITY_OPT_IN";
+
+/// The HTTP semantic conventions recorded on request spans.
+///
+/// The stable conventions rename most of the HTTP attributes, so this allows migrating
+/// dashboards and queries without breaking them:
+///
+/// | Legacy             | Stable                                         |
+/// |--------------------|------------------------------------------------|
+/// | `http.method`      | `http.request.method`                          |
+/// | `http.version`     | `network.protocol.version`                     |
+/// | `http.status_code` | `http.response.status_code`                    |
+/// | `http.target`      | `url.path` and `url.query`                     |
+/// | `http.scheme`      | `url.scheme`                                   |
+/// | `http.host`        | `server.address` and `server.port`             |
+/// | `http.client_ip`   | `client.address`                               |
+/// | `http.user_agent`  | `user_agent.original`                          |
+///
+/// In the stable conventions, methods that are not known to the specification are
+/// recorded as `_OTHER`, with the original value in `http.request.method_original`.
+///
+/// # Example
+///
+/// ```rust
+/// use axum_otel::{AxumOtelOnResponse, AxumOtelSpanCreator, HttpSemConv};
+///
+/// let make_span = AxumOtelSpanCreator::new().semconv(HttpSemConv::Dual);
+/// let on_response = AxumOtelOnResponse::new().semconv(HttpSemConv::Dual);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpSemConv {
+    /// Only record the legacy attributes.
+    #[default]
+    Legacy,
+    /// Only record the stable attributes.
+    Stable,
+    /// Record both the legacy and the stable attributes.
+    Dual,
+}
+
+impl HttpSemConv {
+    /// Read the conventions from the `OTEL_SEMCONV_STABILITY_OPT_IN` environment variable.
+    ///
+    /// `http` selects [`HttpSemConv::Stable`] and `http/dup` selects [`HttpSemConv::Dual`],
+    /// following the OpenTelemetry HTTP migration guide. Anything else selects
+    /// [`HttpSemConv::Legacy`].
+    pub fn from_env() -> Self {
+        std::env::var(SEMCONV_STABILITY_OPT_IN)
+            .map(|value| Self::from_opt_in(&value))
+            .unwrap_or_default()
+    }
+
+    fn from_opt_in(value: &str) -> Self {
+        let values = value.split(',').map(str::trim);
+        let mut semconv = Self::Legacy;
+        for value in values {
+            match value {
+                "http/dup" => return Self::Dual,
+                "http" => semconv = Self::Stable,
+                _ => {}
+            }
+        }
+        semconv
+    }
+
+    /// Whether the legacy attributes are recorded.
+    pub fn emit_legacy(self) -> bool {
+        matches!(self, Self::Legacy | Self::Dual)
+    }
+
+    /// Whether the stable attributes are recorded.
+    pub fn emit_stable(self) -> bool {
+        matches!(self, Self::Stable | Self::Dual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelOnResponse, AxumOtelSpanCreator,
+    };
+    use axum::http::{Request, Response};
+    use std::time::Duration;
+    use tower_http::trace::{MakeSpan, OnResponse};
+    use tracing_otel_extra::extract::schema;
+
+    #[test]
+    fn test_from_opt_in() {
+        assert_eq!(HttpSemConv::from_opt_in("http"), HttpSemConv::Stable);
+        assert_eq!(HttpSemConv::from_opt_in("http/dup"), HttpSemConv::Dual);
+        assert_eq!(HttpSemConv::from_opt_in(""), HttpSemConv::Legacy);
+        assert_eq!(HttpSemConv::from_opt_in("database"), HttpSemConv::Legacy);
+        assert_eq!(HttpSemConv::from_opt_in("HTTP"), HttpSemConv::Legacy);
+        assert_eq!(
+            HttpSemConv::from_opt_in("database, http"),
+            HttpSemConv::Stable
+        );
+        assert_eq!(HttpSemConv::from_opt_in("http,http/dup"), HttpSemConv::Dual);
+        assert_eq!(HttpSemConv::from_opt_in("http/dup,http"), HttpSemConv::Dual);
+    }
+
+    #[test]
+    fn test_attributes_of_each_convention() {
+        const LEGACY: [&str; 5] = [
+            schema::HTTP_METHOD,
+            schema::HTTP_STATUS_CODE,
+            schema::HTTP_TARGET,
+            schema::HTTP_SCHEME,
+            schema::HTTP_USER_AGENT,
+        ];
+        const STABLE: [&str; 9] = [
+            schema::HTTP_REQUEST_METHOD,
+            schema::HTTP_RESPONSE_STATUS_CODE,
+            schema::URL_PATH,
+            schema::URL_QUERY,
+            schema::URL_SCHEME,
+            schema::SERVER_ADDRESS,
+            schema::SERVER_PORT,
+            schema::NETWORK_PROTOCOL_VERSION,
+            schema::USER_AGENT_ORIGINAL,
+        ];
+
+        for (semconv, legacy, stable) in [
+            (HttpSemConv::Legacy, true, false),
+            (HttpSemConv::Stable, false, true),
+            (HttpSemConv::Dual, true, true),
+        ] {
+            let collector = SpanCollector::install();
+            let request = Request::get("https://example.com:8443/users?page=2")
+                .header("user-agent", "curl/8.0")
+                .body(())
+                .unwrap();
+            let span = AxumOtelSpanCreator::new()
+                .semconv(semconv)
+                .make_span(&request);
+            AxumOtelOnResponse::new().semconv(semconv).on_response(
+                &Response::new(()),
+                Duration::ZERO,
+                &span,
+            );
+            drop(span);
+
+            let spans = collector.spans();
+            for key in LEGACY {
+                let recorded = attribute(&spans[0], key).is_some();
+                assert_eq!(recorded, legacy, "{key} with {semconv:?}");
+            }
+            for key in STABLE {
+                let recorded = attribute(&spans[0], key).is_some();
+                assert_eq!(recorded, stable, "{key} with {semconv:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_stable_values() {
+        let collector = SpanCollector::install();
+        let request = Request::get("https://example.com:8443/users?page=2")
+            .body(())
+            .unwrap();
+        let span = AxumOtelSpanCreator::new()
+            .semconv(HttpSemConv::Stable)
+            .make_span(&request);
+        AxumOtelOnResponse::new()
+            .semconv(HttpSemConv::Stable)
+            .on_response(&Response::new(()), Duration::ZERO, &span);
+        drop(span);
+
+        let spans = collector.spans();
+        let span = &spans[0];
+        assert_eq!(span.name, "GET");
+        assert_eq!(
+            attribute(span, schema::HTTP_REQUEST_METHOD),
+            Some("GET".into())
+        );
+        assert_eq!(
+            attribute(span, schema::HTTP_RESPONSE_STATUS_CODE),
+            Some(200.into())
+        );
+        assert_eq!(attribute(span, schema::URL_PATH), Some("/users".into()));
+        assert_eq!(attribute(span, schema::URL_QUERY), Some("page=2".into()));
+        assert_eq!(attribute(span, schema::URL_SCHEME), Some("https".into()));
+        assert_eq!(
+            attribute(span, schema::SERVER_ADDRESS),
+            Some("example.com".into())
+        );
+        assert_eq!(attribute(span, schema::SERVER_PORT), Some(8443.into()));
+        assert_eq!(
+            attribute(span, schema::NETWORK_PROTOCOL_VERSION),
+            Some("1.1".into())
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ResponseInfo;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::dyn_event;
+
+/// Latency thresholds above which [`AxumOtelOnResponse`] reports requests as slow.
+///
+/// A slow request gets the `http.request.slow` attribute and a "slow request" event,
+/// at [`Level::WARN`] by default, so it stands out from the regular "finished
+/// processing request" events. With [`SlowRequests::keep`], the span also gets
+/// `sampling.priority` set to `1`, which tail-based samplers can use to keep the trace.
+/// It does not force sampling: the attribute is set once the response is ready, long
+/// after the head sampler decided whether the request span is sampled.
+///
+/// The latency is the one of the "finished processing request" event, from the request
+/// to the response headers. Route thresholds are looked up by the route pattern the
+/// request matched, such as `/users/{id}`, and take precedence over the global
+/// threshold. The route is read from the response extensions, where [`OtelContextLayer`]
+/// copies it; [`AxumOtelLayer`] applies that layer, with a plain [`TraceLayer`] add it
+/// inside the trace layer or only the global threshold applies.
+///
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+/// [`OtelContextLayer`]: crate::OtelContextLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum_otel::{AxumOtelOnResponse, OtelContextLayer, SlowRequests};
+/// use std::time::Duration;
+/// use tower::ServiceBuilder;
+/// use tower_http::trace::TraceLayer;
+///
+/// let layer = ServiceBuilder::new()
+///     .layer(
+///         TraceLayer::new_for_http().on_response(
+///             AxumOtelOnResponse::new().slow_requests(
+///                 SlowRequests::new()
+///                     .threshold(Duration::from_millis(500))
+///                     .route("/reports/{id}", Duration::from_secs(5))
+///                     .keep(true),
+///             ),
+///         ),
+///     )
+///     .layer(OtelContextLayer::new());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SlowRequests {
+    threshold: Option<Duration>,
+    routes: Arc<HashMap<String, Duration>>,
+    level: Level,
+    keep: bool,
+}
+
+impl Default for SlowRequests {
+    fn default() -> Self {
+        Self {
+            threshold: None,
+            routes: Arc::default(),
+            level: Level::WARN,
+            keep: false,
+        }
+    }
+}
+
+impl SlowRequests {
+    /// Create a new `SlowRequests` without thresholds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the threshold for all routes without their own threshold.
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Set the threshold for the given route pattern.
+    pub fn route(mut self, route: impl Into<String>, threshold: Duration) -> Self {
+        Arc::make_mut(&mut self.routes).insert(route.into(), threshold);
+        self
+    }
+
+    /// Set the [`Level`] used for the "slow request" event.
+    ///
+    /// Defaults to [`Level::WARN`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set whether slow requests get `sampling.priority` set to `1`.
+    ///
+    /// Defaults to `false`.
+    pub fn keep(mut self, keep: bool) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// The threshold that applies to the response.
+    pub fn threshold_for(&self, response: &ResponseInfo<'_>) -> Option<Duration> {
+        response
+            .matched_path()
+            .and_then(|route| self.routes.get(route).copied())
+            .or(self.threshold)
+    }
+
+    /// Record the request on the span if it is slower than its threshold.
+    pub(crate) fn record(&self, response: &ResponseInfo<'_>, latency: Duration, span: &Span) {
+        let Some(threshold) = self.threshold_for(response) else {
+            return;
+        };
+        if latency <= threshold {
+            return;
+        }
+
+        span.set_attribute("http.request.slow", true);
+        if self.keep {
+            span.set_attribute("sampling.priority", 1);
+        }
+        span.in_scope(|| {
+            dyn_event!(
+                self.level,
+                latency = latency.as_millis() as u64,
+                threshold = threshold.as_millis() as u64,
+                "slow request"
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelLayer, AxumOtelOnResponse, AxumOtelSpanCreator, OtelContextLayer,
+    };
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use opentelemetry::Value;
+    use tower::{ServiceBuilder, ServiceExt};
+    use tower_http::trace::TraceLayer;
+
+    fn app(slow_requests: SlowRequests) -> Router {
+        let sleep = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        Router::new()
+            .route("/reports/{id}", get(sleep))
+            .route("/", get(sleep))
+            .layer(AxumOtelLayer::new().slow_requests(slow_requests))
+    }
+
+    async fn send(app: Router, uri: &str) {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_is_reported() {
+        let collector = SpanCollector::install();
+        let slow = SlowRequests::new()
+            .threshold(Duration::from_millis(1))
+            .route("/reports/{id}", Duration::from_secs(60))
+            .keep(true);
+        send(app(slow.clone()), "/").await;
+        send(app(slow), "/reports/1").await;
+
+        let spans = collector.spans();
+        let slow_event = |span: &opentelemetry_sdk::trace::SpanData| {
+            span.events.iter().any(|event| event.name == "slow request")
+        };
+        let home = &spans[0];
+        assert_eq!(attribute(home, "http.route"), Some("/".into()));
+        assert_eq!(
+            attribute(home, "http.request.slow"),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(attribute(home, "sampling.priority"), Some(1.into()));
+        assert!(slow_event(home));
+
+        let report = &spans[1];
+        assert_eq!(
+            attribute(report, "http.route"),
+            Some("/reports/{id}".into())
+        );
+        assert_eq!(attribute(report, "http.request.slow"), None);
+        assert!(!slow_event(report));
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_is_reported_by_on_response() {
+        let collector = SpanCollector::install();
+        let on_response = AxumOtelOnResponse::new()
+            .slow_requests(SlowRequests::new().threshold(Duration::from_millis(1)));
+        let app = Router::new()
+            .route("/", get(|| tokio::time::sleep(Duration::from_millis(20))))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(AxumOtelSpanCreator::new())
+                    .on_response(on_response),
+            );
+        send(app, "/").await;
+
+        let spans = collector.spans();
+        assert_eq!(
+            attribute(&spans[0], "http.request.slow"),
+            Some(Value::Bool(true))
+        );
+        assert!(spans[0]
+            .events
+            .iter()
+            .any(|event| event.name == "slow request"));
+    }
+
+    #[tokio::test]
+    async fn test_route_threshold_needs_otel_context_layer() {
+        let collector = SpanCollector::install();
+        let slow = SlowRequests::new()
+            .threshold(Duration::from_millis(1))
+            .route("/reports/{id}", Duration::from_secs(60));
+        let trace = TraceLayer::new_for_http()
+            .make_span_with(AxumOtelSpanCreator::new())
+            .on_response(AxumOtelOnResponse::new().slow_requests(slow));
+        let router = Router::new().route(
+            "/reports/{id}",
+            get(|| tokio::time::sleep(Duration::from_millis(20))),
+        );
+
+        // Without the route in the response extensions, the global threshold applies
+        send(router.clone().layer(trace.clone()), "/reports/1").await;
+        let layer = ServiceBuilder::new()
+            .layer(trace)
+            .layer(OtelContextLayer::new());
+        send(router.layer(layer), "/reports/1").await;
+
+        let spans = collector.spans();
+        assert_eq!(
+            attribute(&spans[0], "http.request.slow"),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(attribute(&spans[1], "http.request.slow"), None);
+    }
+}
@@ -0,0 +1,200 @@
+use axum::http::StatusCode;
+use std::{fmt, sync::Arc};
+
+type Classify = Arc<dyn Fn(StatusCode) -> SpanStatus + Send + Sync>;
+
+/// The OpenTelemetry status of a request span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanStatus {
+    /// Leave the status unset.
+    Unset,
+    /// Set the status to `OK`.
+    Ok,
+    /// Set the status to `ERROR`.
+    Error,
+}
+
+impl SpanStatus {
+    /// The value recorded as `otel.status_code`, or `None` if the status is unset.
+    pub(crate) fn as_otel_status_code(self) -> Option<&'static str> {
+        match self {
+            Self::Unset => None,
+            Self::Ok => Some("OK"),
+            Self::Error => Some("ERROR"),
+        }
+    }
+}
+
+/// Maps response status codes to the [`SpanStatus`] of the request span.
+///
+/// By default `5xx` responses are errors and all other responses are `OK`. The
+/// OpenTelemetry specification recommends leaving the status of server spans unset
+/// unless the request failed, which [`StatusPolicy::success`] allows.
+///
+/// gRPC responses carry their status in the `grpc-status` header or trailer, not in the
+/// HTTP status code, so [`AxumOtelOnResponse`] leaves their span status unset and the
+/// gRPC classifier of `TraceLayer::new_for_grpc` marks failures with
+/// [`AxumOtelOnFailure`].
+///
+/// [`AxumOtelOnResponse`]: crate::AxumOtelOnResponse
+/// [`AxumOtelOnFailure`]: crate::AxumOtelOnFailure
+///
+/// # Example
+///
+/// ```rust
+/// use axum::http::StatusCode;
+/// use axum_otel::{AxumOtelLayer, SpanStatus, StatusPolicy};
+///
+/// // Follow the specification: unset for non-errors, errors for 5xx
+/// let policy = StatusPolicy::new().success(SpanStatus::Unset);
+///
+/// // A custom mapping replaces the other settings, here errors for 5xx and 429
+/// let custom = StatusPolicy::new().custom(|status| match status {
+///     StatusCode::TOO_MANY_REQUESTS => SpanStatus::Error,
+///     status if status.is_server_error() => SpanStatus::Error,
+///     _ => SpanStatus::Unset,
+/// });
+///
+/// let layer = AxumOtelLayer::new().status_policy(policy);
+/// ```
+#[derive(Clone)]
+pub struct StatusPolicy {
+    success: SpanStatus,
+    client_errors: bool,
+    custom: Option<Classify>,
+}
+
+impl StatusPolicy {
+    /// Create a new `StatusPolicy` with the default mapping.
+    pub fn new() -> Self {
+        Self {
+            success: SpanStatus::Ok,
+            client_errors: false,
+            custom: None,
+        }
+    }
+
+    /// Set the [`SpanStatus`] of responses that are not errors.
+    ///
+    /// Defaults to [`SpanStatus::Ok`].
+    pub fn success(mut self, status: SpanStatus) -> Self {
+        self.success = status;
+        self
+    }
+
+    /// Set whether `4xx` responses are errors.
+    ///
+    /// Defaults to `false`.
+    pub fn client_errors(mut self, enabled: bool) -> Self {
+        self.client_errors = enabled;
+        self
+    }
+
+    /// Map status codes with a custom closure.
+    ///
+    /// The closure replaces the other settings, [`StatusPolicy::success`] and
+    /// [`StatusPolicy::client_errors`] are ignored once it is set.
+    pub fn custom<F>(mut self, classify: F) -> Self
+    where
+        F: Fn(StatusCode) -> SpanStatus + Send + Sync + 'static,
+    {
+        self.custom = Some(Arc::new(classify));
+        self
+    }
+
+    /// Returns the [`SpanStatus`] for the response status code.
+    pub fn classify(&self, status: StatusCode) -> SpanStatus {
+        if let Some(custom) = &self.custom {
+            return custom(status);
+        }
+        if status.is_server_error() || (self.client_errors && status.is_client_error()) {
+            SpanStatus::Error
+        } else {
+            self.success
+        }
+    }
+}
+
+impl Default for StatusPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StatusPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatusPolicy")
+            .field("success", &self.success)
+            .field("client_errors", &self.client_errors)
+            .field("custom", &self.custom.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::SpanCollector, AxumOtelOnFailure, AxumOtelOnResponse, AxumOtelSpanCreator,
+    };
+    use axum::{body::Body, http::Request, routing::post, Router};
+    use opentelemetry::trace::Status;
+    use tower::ServiceExt;
+    use tower_http::trace::TraceLayer;
+
+    #[test]
+    fn test_status_policy() {
+        let policy = StatusPolicy::new();
+        assert_eq!(policy.classify(StatusCode::OK), SpanStatus::Ok);
+        assert_eq!(policy.classify(StatusCode::NOT_FOUND), SpanStatus::Ok);
+        assert_eq!(
+            policy.classify(StatusCode::INTERNAL_SERVER_ERROR),
+            SpanStatus::Error
+        );
+
+        let policy = StatusPolicy::new()
+            .success(SpanStatus::Unset)
+            .client_errors(true);
+        assert_eq!(policy.classify(StatusCode::OK), SpanStatus::Unset);
+        assert_eq!(policy.classify(StatusCode::NOT_FOUND), SpanStatus::Error);
+
+        let policy = StatusPolicy::new().custom(|_| SpanStatus::Unset);
+        assert_eq!(
+            policy.classify(StatusCode::INTERNAL_SERVER_ERROR),
+            SpanStatus::Unset
+        );
+    }
+
+    async fn grpc_span_status(grpc_status: &'static str) -> Status {
+        let collector = SpanCollector::install();
+        let app = Router::new()
+            .route(
+                "/users.Users/Get",
+                post(move || async move {
+                    [
+                        ("content-type", "application/grpc"),
+                        ("grpc-status", grpc_status),
+                    ]
+                }),
+            )
+            .layer(
+                TraceLayer::new_for_grpc()
+                    .make_span_with(AxumOtelSpanCreator::new())
+                    .on_response(AxumOtelOnResponse::new())
+                    .on_failure(AxumOtelOnFailure::new()),
+            );
+        let request = Request::post("/users.Users/Get")
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        collector.spans()[0].status.clone()
+    }
+
+    #[tokio::test]
+    async fn test_grpc_status_is_classified_by_the_grpc_classifier() {
+        assert_eq!(grpc_span_status("0").await, Status::Unset);
+        // INTERNAL, although the HTTP status is 200
+        assert_eq!(grpc_span_status("13").await, Status::error(""));
+    }
+}
@@ -0,0 +1,219 @@
+//! Helpers shared by the unit tests.
+
+use opentelemetry::{
+    metrics::{Meter, MeterProvider as _},
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_sdk::{
+    metrics::{
+        data::{AggregatedMetrics, MetricData},
+        InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+    },
+    trace::{InMemorySpanExporter, SdkTracerProvider, SpanData},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+use tracing::{
+    field::{Field, Visit},
+    subscriber::DefaultGuard,
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, Identity, SubscriberExt},
+    Layer, Registry,
+};
+
+/// Exports the spans of the current thread to memory until dropped.
+pub(crate) struct SpanCollector {
+    exporter: InMemorySpanExporter,
+    provider: SdkTracerProvider,
+    _guard: DefaultGuard,
+}
+
+impl SpanCollector {
+    /// Install a subscriber that exports the closed spans of the current thread.
+    pub(crate) fn install() -> Self {
+        Self::install_with(Identity::new())
+    }
+
+    /// Install a subscriber that exports the closed spans of the current thread, and
+    /// passes spans and events to `layer` as well.
+    pub(crate) fn install_with<L>(layer: L) -> Self
+    where
+        L: Layer<Registry> + Send + Sync,
+    {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(layer)
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        Self {
+            exporter,
+            provider,
+            _guard: tracing::subscriber::set_default(subscriber),
+        }
+    }
+
+    /// The spans closed so far.
+    pub(crate) fn spans(&self) -> Vec<SpanData> {
+        self.provider.force_flush().unwrap();
+        self.exporter.get_finished_spans().unwrap()
+    }
+}
+
+/// Returns the value of the attribute, if the span has it.
+pub(crate) fn attribute(span: &SpanData, key: &str) -> Option<opentelemetry::Value> {
+    span.attributes
+        .iter()
+        .find(|attribute| attribute.key.as_str() == key)
+        .map(|attribute| attribute.value.clone())
+}
+
+/// Collects the fields of the events with the given target.
+#[derive(Clone)]
+pub(crate) struct EventCollector {
+    target: &'static str,
+    events: Arc<Mutex<Vec<HashMap<&'static str, String>>>>,
+}
+
+impl EventCollector {
+    pub(crate) fn new(target: &'static str) -> Self {
+        Self {
+            target,
+            events: Arc::default(),
+        }
+    }
+
+    /// The fields of the events collected so far, with the message as `message`.
+    pub(crate) fn events(&self) -> Vec<HashMap<&'static str, String>> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventCollector {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        struct Visitor(HashMap<&'static str, String>);
+        impl Visit for Visitor {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.insert(field.name(), value.to_owned());
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                self.0.insert(field.name(), format!("{:?}", value));
+            }
+        }
+
+        if event.metadata().target() == self.target {
+            let mut visitor = Visitor(HashMap::new());
+            event.record(&mut visitor);
+            self.events.lock().unwrap().push(visitor.0);
+        }
+    }
+}
+
+/// Exports the measurements of a meter to memory.
+pub(crate) struct MetricCollector {
+    exporter: InMemoryMetricExporter,
+    provider: SdkMeterProvider,
+}
+
+/// A data point of a sum or histogram, with the sum value or the histogram count.
+#[derive(Debug)]
+pub(crate) struct DataPoint {
+    pub(crate) attributes: Vec<KeyValue>,
+    pub(crate) value: f64,
+}
+
+impl DataPoint {
+    /// Returns the value of the attribute, if the data point has it.
+    pub(crate) fn attribute(&self, key: &str) -> Option<opentelemetry::Value> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| attribute.value.clone())
+    }
+}
+
+impl MetricCollector {
+    pub(crate) fn new() -> Self {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        Self { exporter, provider }
+    }
+
+    pub(crate) fn meter(&self) -> Meter {
+        self.provider.meter("test")
+    }
+
+    /// The data points of the metric, as of now.
+    pub(crate) fn data_points(&self, name: &str) -> Vec<DataPoint> {
+        self.exporter.reset();
+        self.provider.force_flush().unwrap();
+        let metrics = self.exporter.get_finished_metrics().unwrap();
+        let Some(metric) = metrics
+            .iter()
+            .flat_map(|metrics| metrics.scope_metrics())
+            .flat_map(|scope| scope.metrics())
+            .find(|metric| metric.name() == name)
+        else {
+            return Vec::new();
+        };
+
+        fn points<T: Copy + ToF64>(data: &MetricData<T>) -> Vec<DataPoint> {
+            match data {
+                MetricData::Sum(sum) => sum
+                    .data_points()
+                    .map(|point| DataPoint {
+                        attributes: point.attributes().cloned().collect(),
+                        value: point.value().to_f64(),
+                    })
+                    .collect(),
+                MetricData::Histogram(histogram) => histogram
+                    .data_points()
+                    .map(|point| DataPoint {
+                        attributes: point.attributes().cloned().collect(),
+                        value: point.count() as f64,
+                    })
+                    .collect(),
+                // Gauges and exponential histograms are not recorded by the middleware
+                _ => Vec::new(),
+            }
+        }
+        match metric.data() {
+            AggregatedMetrics::F64(data) => points(data),
+            AggregatedMetrics::U64(data) => points(data),
+            AggregatedMetrics::I64(data) => points(data),
+        }
+    }
+}
+
+/// Converts the value of a data point for comparisons.
+trait ToF64 {
+    fn to_f64(self) -> f64;
+}
+
+impl ToF64 for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl ToF64 for u64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl ToF64 for i64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
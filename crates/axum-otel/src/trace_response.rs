@@ -0,0 +1,387 @@
+use axum::http::{header, HeaderName, HeaderValue, Request, Response};
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower::{Layer, Service};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::extract::http::inject_context_into_response;
+
+/// The W3C Trace Context Level 2 response header.
+pub const TRACERESPONSE: HeaderName = HeaderName::from_static("traceresponse");
+
+/// A commonly used header for exposing the trace id.
+pub const X_TRACE_ID: HeaderName = HeaderName::from_static("x-trace-id");
+
+/// The `Server-Timing` header, which `http::header` does not define.
+pub const SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+/// A [`Layer`] that writes the trace context of the request span into the response headers.
+///
+/// This lets browsers, frontends and support tooling find the trace of a request
+/// without access to the backend. The following headers can be written:
+///
+/// - `traceresponse`: the W3C Trace Context Level 2 response header, e.g.
+///   `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+/// - A trace id header, such as `x-trace-id`, carrying only the trace id
+/// - `Server-Timing`: the total latency and the `traceparent` of the request
+/// - The headers of the global text map propagator, using
+///   [`inject_context_into_response`]
+///
+/// The layer must be applied inside a [`TraceLayer`] so the request span is the
+/// current span, which [`AxumOtelLayer`] takes care of.
+///
+/// Browsers only expose these headers to cross-origin scripts when they are listed in
+/// `Access-Control-Expose-Headers`. With [`TraceContextResponseLayer::expose_headers`]
+/// the layer appends them to that header itself. When an outer `CorsLayer` sets the
+/// header it replaces the value, so pass [`TraceContextResponseLayer::header_names`]
+/// to `CorsLayer::expose_headers` instead.
+///
+/// [`TraceLayer`]: tower_http::trace::TraceLayer
+/// [`AxumOtelLayer`]: crate::AxumOtelLayer
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{routing::get, Router};
+/// use axum_otel::{AxumOtelLayer, TraceContextResponseLayer, X_TRACE_ID};
+///
+/// async fn handler() -> &'static str {
+///     "Hello, world!"
+/// }
+///
+/// let app: Router<()> = Router::new().route("/", get(handler)).layer(
+///     AxumOtelLayer::new().trace_context_response(
+///         TraceContextResponseLayer::new()
+///             .trace_id_header(X_TRACE_ID)
+///             .server_timing(true)
+///             .expose_headers(true),
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct TraceContextResponseLayer {
+    traceresponse: bool,
+    trace_id_header: Option<HeaderName>,
+    server_timing: bool,
+    propagate: bool,
+    expose_headers: bool,
+}
+
+impl TraceContextResponseLayer {
+    /// Create a new `TraceContextResponseLayer` that writes the `traceresponse` header.
+    pub fn new() -> Self {
+        Self {
+            traceresponse: true,
+            trace_id_header: None,
+            server_timing: false,
+            propagate: false,
+            expose_headers: false,
+        }
+    }
+
+    /// Set whether the `traceresponse` header is written.
+    ///
+    /// Defaults to `true`.
+    pub fn traceresponse(mut self, enabled: bool) -> Self {
+        self.traceresponse = enabled;
+        self
+    }
+
+    /// Write the trace id into the given header, such as [`X_TRACE_ID`].
+    ///
+    /// By default no trace id header is written.
+    pub fn trace_id_header(mut self, header_name: HeaderName) -> Self {
+        self.trace_id_header = Some(header_name);
+        self
+    }
+
+    /// Set whether a `Server-Timing` header with the total latency and the
+    /// `traceparent` is written.
+    ///
+    /// Defaults to `false`.
+    pub fn server_timing(mut self, enabled: bool) -> Self {
+        self.server_timing = enabled;
+        self
+    }
+
+    /// Set whether the headers of the global text map propagator are written.
+    ///
+    /// Defaults to `false`.
+    pub fn propagate(mut self, enabled: bool) -> Self {
+        self.propagate = enabled;
+        self
+    }
+
+    /// Set whether the written headers are appended to `Access-Control-Expose-Headers`.
+    ///
+    /// Defaults to `false`.
+    pub fn expose_headers(mut self, enabled: bool) -> Self {
+        self.expose_headers = enabled;
+        self
+    }
+
+    /// The names of the headers written by this layer.
+    ///
+    /// Headers written by the global text map propagator are not included.
+    pub fn header_names(&self) -> Vec<HeaderName> {
+        let mut names = Vec::new();
+        if self.traceresponse {
+            names.push(TRACERESPONSE);
+        }
+        if let Some(header_name) = &self.trace_id_header {
+            names.push(header_name.clone());
+        }
+        if self.server_timing {
+            names.push(SERVER_TIMING);
+        }
+        names
+    }
+
+    fn write_headers<B>(&self, response: &mut Response<B>, span: &Span, start: Instant) {
+        let context = span.context();
+        let span_ref = context.span();
+        let span_context = span_ref.span_context();
+        if !span_context.is_valid() {
+            return;
+        }
+
+        let traceparent = format_traceparent(span_context);
+        let headers = response.headers_mut();
+        if self.traceresponse {
+            if let Ok(value) = HeaderValue::from_str(&traceparent) {
+                headers.insert(TRACERESPONSE, value);
+            }
+        }
+        if let Some(header_name) = &self.trace_id_header {
+            if let Ok(value) = HeaderValue::from_str(&span_context.trace_id().to_string()) {
+                headers.insert(header_name.clone(), value);
+            }
+        }
+        if self.server_timing {
+            let value = format!(
+                "total;dur={:.3}, traceparent;desc=\"{}\"",
+                start.elapsed().as_secs_f64() * 1000.0,
+                traceparent
+            );
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.append(SERVER_TIMING, value);
+            }
+        }
+        if self.expose_headers {
+            append_expose_headers(headers, &self.header_names());
+        }
+        if self.propagate {
+            inject_context_into_response(&context, response);
+        }
+    }
+}
+
+impl Default for TraceContextResponseLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for TraceContextResponseLayer {
+    type Service = TraceContextResponse<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContextResponse {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Middleware that writes the trace context of the request span into the response headers.
+///
+/// See [`TraceContextResponseLayer`] for more details.
+#[derive(Clone, Debug)]
+pub struct TraceContextResponse<S> {
+    inner: S,
+    layer: TraceContextResponseLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for TraceContextResponse<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        ResponseFuture {
+            inner: self.inner.call(request),
+            layer: self.layer.clone(),
+            span: Span::current(),
+            start: Instant::now(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`TraceContextResponse`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        layer: TraceContextResponseLayer,
+        span: Span,
+        start: Instant,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut result = ready!(this.inner.poll(cx));
+        if let Ok(response) = &mut result {
+            this.layer.write_headers(response, this.span, *this.start);
+        }
+        Poll::Ready(result)
+    }
+}
+
+/// Format the span context as a W3C `traceparent` value.
+pub(crate) fn format_traceparent(span_context: &SpanContext) -> String {
+    format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8()
+    )
+}
+
+/// Append the header names to `Access-Control-Expose-Headers`, keeping existing values.
+fn append_expose_headers(headers: &mut axum::http::HeaderMap, names: &[HeaderName]) {
+    let existing: Vec<String> = headers
+        .get_all(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if existing.iter().any(|name| name == "*") {
+        return;
+    }
+
+    let missing: Vec<&str> = names
+        .iter()
+        .map(HeaderName::as_str)
+        .filter(|name| !existing.iter().any(|existing| existing == name))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let value = existing
+        .iter()
+        .map(String::as_str)
+        .chain(missing)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::SpanCollector, AxumOtelLayer};
+    use axum::{body::Body, http::HeaderMap, routing::get, Router};
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    fn layer() -> TraceContextResponseLayer {
+        TraceContextResponseLayer::new()
+            .trace_id_header(X_TRACE_ID)
+            .server_timing(true)
+    }
+
+    #[tokio::test]
+    async fn test_headers_are_written_for_the_request_span() {
+        let collector = SpanCollector::install();
+        let app = Router::new()
+            .route("/", get(|| async { "Hello, world!" }))
+            .layer(AxumOtelLayer::new().trace_context_response(layer()));
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let headers = response.headers().clone();
+        drop(response);
+
+        let spans = collector.spans();
+        let span_context = &spans[0].span_context;
+        let traceparent = format_traceparent(span_context);
+        assert_eq!(headers[TRACERESPONSE], traceparent.as_str());
+        assert_eq!(
+            headers[X_TRACE_ID],
+            span_context.trace_id().to_string().as_str()
+        );
+
+        let server_timing = headers[SERVER_TIMING].to_str().unwrap();
+        assert!(server_timing.starts_with("total;dur="));
+        assert!(server_timing.ends_with(&format!("traceparent;desc=\"{}\"", traceparent)));
+    }
+
+    #[tokio::test]
+    async fn test_headers_are_skipped_for_invalid_span_contexts() {
+        let _collector = SpanCollector::install();
+        // Without a request span the current span context is invalid
+        let service = layer().layer(service_fn(|_: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key(TRACERESPONSE));
+        assert!(!response.headers().contains_key(X_TRACE_ID));
+        assert!(!response.headers().contains_key(SERVER_TIMING));
+    }
+
+    #[test]
+    fn test_append_expose_headers() {
+        let mut headers = HeaderMap::new();
+        append_expose_headers(&mut headers, &[TRACERESPONSE, X_TRACE_ID]);
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "traceresponse, x-trace-id"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderValue::from_static("X-Request-Id, x-trace-id"),
+        );
+        append_expose_headers(&mut headers, &[TRACERESPONSE, X_TRACE_ID]);
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-request-id, x-trace-id, traceresponse"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderValue::from_static("*"),
+        );
+        append_expose_headers(&mut headers, &[TRACERESPONSE]);
+        assert_eq!(headers[header::ACCESS_CONTROL_EXPOSE_HEADERS], "*");
+    }
+}
@@ -0,0 +1,431 @@
+use crate::OtelContext;
+use axum::{
+    extract::ws::{Message, WebSocket},
+    Error,
+};
+use futures_core::Stream;
+use futures_sink::Sink;
+use opentelemetry::trace::TraceContextExt;
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tracing::{field::Empty, Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_otel_extra::dyn_span;
+
+/// Configures how [`TracedWebSocket`] traces a WebSocket connection.
+///
+/// The request span of the upgrade request closes with the `101 Switching Protocols`
+/// response, so each connection gets its own long-lived `websocket` span. The span
+/// starts a new trace, linked to the upgrade request span, and records the following
+/// attributes when the connection is dropped:
+///
+/// - `websocket.duration_ms`: The connection duration
+/// - `websocket.close_code`: The close code sent or received, if any
+/// - `websocket.messages_received` and `websocket.bytes_received`: Received messages
+/// - `websocket.messages_sent` and `websocket.bytes_sent`: Sent messages, counted once
+///   they were flushed successfully
+///
+/// With [`WebSocketTracing::message_level`], every message also gets a
+/// `websocket.message` child span with its direction, type and size.
+///
+/// [`TracedWebSocket`] implements [`Stream`] and [`Sink`] like [`WebSocket`], so it can be
+/// split into a sender and a receiver. The connection is recorded once both halves are
+/// dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use axum::{
+///     extract::ws::{WebSocket, WebSocketUpgrade},
+///     response::Response,
+///     routing::get,
+///     Router,
+/// };
+/// use axum_otel::{AxumOtelRouterExt, Level, OtelContext, WebSocketTracing};
+///
+/// async fn handler(ws: WebSocketUpgrade, context: OtelContext) -> Response {
+///     ws.on_upgrade(move |socket| async move {
+///         let mut socket = WebSocketTracing::new()
+///             .message_level(Level::DEBUG)
+///             .wrap(socket, &context);
+///         while let Some(Ok(message)) = socket.recv().await {
+///             if socket.send(message).await.is_err() {
+///                 break;
+///             }
+///         }
+///     })
+/// }
+///
+/// let app: Router<()> = Router::new().route("/ws", get(handler)).with_otel();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WebSocketTracing {
+    level: Level,
+    message_level: Option<Level>,
+}
+
+impl Default for WebSocketTracing {
+    fn default() -> Self {
+        Self {
+            level: Level::INFO,
+            message_level: None,
+        }
+    }
+}
+
+impl WebSocketTracing {
+    /// Create a new `WebSocketTracing`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Level`] used for the `websocket` span.
+    ///
+    /// Defaults to [`Level::INFO`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Create a `websocket.message` span at the given [`Level`] for every message.
+    ///
+    /// By default no message spans are created.
+    pub fn message_level(mut self, level: Level) -> Self {
+        self.message_level = Some(level);
+        self
+    }
+
+    /// Wrap the socket, linking its span to the upgrade request in the [`OtelContext`].
+    pub fn wrap(&self, socket: WebSocket, context: &OtelContext) -> TracedWebSocket {
+        let span = dyn_span!(
+            self.level,
+            "websocket",
+            otel.name = "websocket",
+            otel.kind = "server",
+            websocket.protocol = socket
+                .protocol()
+                .and_then(|protocol| protocol.to_str().ok()),
+            websocket.close_code = Empty,
+            websocket.duration_ms = Empty,
+            websocket.messages_received = Empty,
+            websocket.bytes_received = Empty,
+            websocket.messages_sent = Empty,
+            websocket.bytes_sent = Empty,
+        );
+        // Start a new trace, the connection outlives the upgrade request
+        span.set_parent(opentelemetry::Context::new());
+        let request_span = context.0.span();
+        let request_span_context = request_span.span_context();
+        if request_span_context.is_valid() {
+            span.add_link(request_span_context.clone());
+        }
+
+        TracedWebSocket {
+            socket,
+            span,
+            message_level: self.message_level,
+            start: Instant::now(),
+            received: MessageCounts::default(),
+            sent: MessageCounts::default(),
+            unflushed: MessageCounts::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct MessageCounts {
+    messages: u64,
+    bytes: u64,
+}
+
+impl MessageCounts {
+    fn add(&mut self, size: usize) {
+        self.messages += 1;
+        self.bytes += size as u64;
+    }
+
+    /// Move the counts of `other` into `self`, leaving `other` empty.
+    fn append(&mut self, other: &mut MessageCounts) {
+        self.messages += other.messages;
+        self.bytes += other.bytes;
+        *other = MessageCounts::default();
+    }
+}
+
+/// A [`WebSocket`] that records the connection and its messages on a `websocket` span.
+///
+/// Created with [`WebSocketTracing::wrap`], see there for details.
+#[derive(Debug)]
+pub struct TracedWebSocket {
+    socket: WebSocket,
+    span: Span,
+    message_level: Option<Level>,
+    start: Instant,
+    received: MessageCounts,
+    sent: MessageCounts,
+    /// Messages accepted by the socket but not flushed yet.
+    unflushed: MessageCounts,
+}
+
+impl TracedWebSocket {
+    /// Receive another message.
+    ///
+    /// Returns `None` if the stream has closed.
+    pub async fn recv(&mut self) -> Option<Result<Message, Error>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Send a message.
+    pub async fn send(&mut self, message: Message) -> Result<(), Error> {
+        let size = message_size(&message);
+        let span = self.message_span("send", &message, size);
+        async {
+            poll_fn(|cx| Pin::new(&mut *self).poll_ready(cx)).await?;
+            self.start_send_sized(message, size)?;
+            poll_fn(|cx| Pin::new(&mut *self).poll_flush(cx)).await
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// The `websocket` span, to instrument work done for the connection.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Return the selected WebSocket subprotocol, if one has been chosen.
+    pub fn protocol(&self) -> Option<&axum::http::HeaderValue> {
+        self.socket.protocol()
+    }
+
+    fn start_send_sized(&mut self, message: Message, size: usize) -> Result<(), Error> {
+        let close_code = close_code(&message);
+        Pin::new(&mut self.socket).start_send(message)?;
+        self.unflushed.add(size);
+        if let Some(close_code) = close_code {
+            self.span.record("websocket.close_code", close_code);
+        }
+        Ok(())
+    }
+
+    fn message_span(&self, direction: &'static str, message: &Message, size: usize) -> Span {
+        let Some(level) = self.message_level else {
+            return Span::none();
+        };
+        self.span.in_scope(|| {
+            dyn_span!(
+                level,
+                "websocket.message",
+                websocket.message.direction = direction,
+                websocket.message.type = message_type(message),
+                websocket.message.size = size as u64,
+            )
+        })
+    }
+}
+
+impl Stream for TracedWebSocket {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = ready!(Pin::new(&mut self.socket).poll_next(cx));
+        if let Some(Ok(message)) = &message {
+            let size = message_size(message);
+            self.received.add(size);
+            if let Some(close_code) = close_code(message) {
+                self.span.record("websocket.close_code", close_code);
+            }
+            // The message already arrived, so its span only carries the attributes
+            let _span = self.message_span("receive", message, size);
+        }
+        Poll::Ready(message)
+    }
+}
+
+impl Sink<Message> for TracedWebSocket {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut self.socket).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: Message) -> Result<(), Error> {
+        let size = message_size(&message);
+        let _span = self.message_span("send", &message, size).entered();
+        self.start_send_sized(message, size)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        ready!(Pin::new(&mut self.socket).poll_flush(cx))?;
+        let this = &mut *self;
+        this.sent.append(&mut this.unflushed);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        ready!(Pin::new(&mut self.socket).poll_close(cx))?;
+        let this = &mut *self;
+        this.sent.append(&mut this.unflushed);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for TracedWebSocket {
+    fn drop(&mut self) {
+        // Recorded as `i64`, `u64` values recorded after the span was created are
+        // exported as strings
+        let span = &self.span;
+        span.record(
+            "websocket.duration_ms",
+            self.start.elapsed().as_millis() as i64,
+        );
+        span.record("websocket.messages_received", self.received.messages as i64);
+        span.record("websocket.bytes_received", self.received.bytes as i64);
+        span.record("websocket.messages_sent", self.sent.messages as i64);
+        span.record("websocket.bytes_sent", self.sent.bytes as i64);
+    }
+}
+
+fn message_type(message: &Message) -> &'static str {
+    match message {
+        Message::Text(_) => "text",
+        Message::Binary(_) => "binary",
+        Message::Ping(_) => "ping",
+        Message::Pong(_) => "pong",
+        Message::Close(_) => "close",
+    }
+}
+
+fn close_code(message: &Message) -> Option<i64> {
+    match message {
+        Message::Close(Some(frame)) => Some(i64::from(frame.code)),
+        _ => None,
+    }
+}
+
+fn message_size(message: &Message) -> usize {
+    match message {
+        Message::Text(text) => text.len(),
+        Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data.len(),
+        Message::Close(frame) => frame.as_ref().map_or(0, |frame| frame.reason.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{attribute, SpanCollector},
+        AxumOtelRouterExt,
+    };
+    use axum::{
+        body::Bytes,
+        extract::ws::{CloseFrame, WebSocketUpgrade},
+        routing::get,
+        Router,
+    };
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::{net::TcpListener, sync::mpsc};
+    use tokio_tungstenite::tungstenite::{self, protocol::frame::coding::CloseCode};
+
+    #[test]
+    fn test_message_type_and_size() {
+        let close = CloseFrame {
+            code: 1000,
+            reason: "bye".into(),
+        };
+        let cases = [
+            (Message::Text("hello".into()), "text", 5),
+            (Message::Binary(Bytes::from_static(b"abc")), "binary", 3),
+            (Message::Ping(Bytes::from_static(b"p")), "ping", 1),
+            (Message::Pong(Bytes::new()), "pong", 0),
+            (Message::Close(Some(close)), "close", 3),
+            (Message::Close(None), "close", 0),
+        ];
+        for (message, message_type_, size) in cases {
+            assert_eq!(message_type(&message), message_type_);
+            assert_eq!(message_size(&message), size);
+        }
+    }
+
+    /// Serves an echo handler on a split socket, and notifies once the socket is dropped.
+    async fn serve_echo(done: mpsc::UnboundedSender<()>) -> String {
+        let handler = move |ws: WebSocketUpgrade, context: OtelContext| {
+            let done = done.clone();
+            async move {
+                ws.on_upgrade(move |socket| async move {
+                    let socket = WebSocketTracing::new()
+                        .message_level(Level::DEBUG)
+                        .wrap(socket, &context);
+                    let (mut sender, mut receiver) = socket.split();
+                    while let Some(Ok(message)) = receiver.next().await {
+                        if matches!(message, Message::Close(_)) {
+                            break;
+                        }
+                        sender.send(message).await.unwrap();
+                    }
+                    drop((sender, receiver));
+                    done.send(()).unwrap();
+                })
+            }
+        };
+        let app = Router::new().route("/ws", get(handler)).with_otel();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}/ws", address)
+    }
+
+    #[tokio::test]
+    async fn test_split_socket_is_recorded_when_dropped() {
+        let collector = SpanCollector::install();
+        let (done, mut dropped) = mpsc::unbounded_channel();
+        let url = serve_echo(done).await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client
+            .send(tungstenite::Message::text("hello"))
+            .await
+            .unwrap();
+        let echo = client.next().await.unwrap().unwrap();
+        assert_eq!(echo, tungstenite::Message::text("hello"));
+        client
+            .close(Some(tungstenite::protocol::CloseFrame {
+                code: CloseCode::Normal,
+                reason: "bye".into(),
+            }))
+            .await
+            .unwrap();
+        dropped.recv().await.unwrap();
+
+        let spans = collector.spans();
+        let socket = spans.iter().find(|span| span.name == "websocket").unwrap();
+        assert_eq!(socket.links.len(), 1);
+        assert_eq!(attribute(socket, "websocket.close_code"), Some(1000.into()));
+        assert_eq!(
+            attribute(socket, "websocket.messages_received"),
+            Some(2.into())
+        );
+        assert_eq!(
+            attribute(socket, "websocket.bytes_received"),
+            Some(8.into())
+        );
+        assert_eq!(attribute(socket, "websocket.messages_sent"), Some(1.into()));
+        assert_eq!(attribute(socket, "websocket.bytes_sent"), Some(5.into()));
+        assert!(attribute(socket, "websocket.duration_ms").is_some());
+
+        let directions: Vec<_> = spans
+            .iter()
+            .filter(|span| span.name == "websocket.message")
+            .map(|span| attribute(span, "websocket.message.direction").unwrap())
+            .collect();
+        assert_eq!(
+            directions,
+            ["receive".into(), "send".into(), "receive".into()]
+        );
+    }
+}
@@ -10,7 +10,7 @@
 //! - `logger`: Basic logging functionality with configurable formats
 //! - `env`: Environment-based logging configuration
 //! - `context`: Trace context utilities
-//! - `fields`: Common tracing fields and attributes
+//! - `fields`: Common tracing fields and attributes, and the field names shared by request spans
 //! - `http`: HTTP request/response tracing
 //! - `span`: Span creation and management utilities
 //!
@@ -107,6 +107,10 @@ pub mod extract {
     #[cfg(feature = "http")]
     pub use crate::trace::http;
 
+    // Schema module exports
+    #[cfg(feature = "fields")]
+    pub use crate::trace::schema;
+
     // Span module exports
     #[cfg(feature = "span")]
     pub use crate::trace::span;
@@ -28,6 +28,9 @@
 /// let request_id = "uuid";
 /// // Emit event with dynamic level
 /// dyn_event!(level, request_id = %request_id, "request");
+///
+/// // Emit event with a custom target
+/// dyn_event!(target: "access_log", level, request_id = %request_id, "request");
 /// ```
 ///
 /// # Comparison with log crate
@@ -42,6 +45,15 @@
 /// The actual event emission is still handled by tracing's efficient filtering system.
 #[macro_export]
 macro_rules! dyn_event {
+    (target: $target:expr, $lvl:expr, $($tt:tt)*) => {
+        match $lvl {
+            tracing::Level::ERROR => tracing::event!(target: $target, tracing::Level::ERROR, $($tt)*),
+            tracing::Level::WARN => tracing::event!(target: $target, tracing::Level::WARN, $($tt)*),
+            tracing::Level::INFO => tracing::event!(target: $target, tracing::Level::INFO, $($tt)*),
+            tracing::Level::DEBUG => tracing::event!(target: $target, tracing::Level::DEBUG, $($tt)*),
+            tracing::Level::TRACE => tracing::event!(target: $target, tracing::Level::TRACE, $($tt)*),
+        }
+    };
     ($lvl:expr, $($tt:tt)*) => {
         match $lvl {
             tracing::Level::ERROR => tracing::event!(tracing::Level::ERROR, $($tt)*),
@@ -73,6 +85,9 @@ macro_rules! dyn_event {
 /// let span = dyn_span!(level, "processing", operation = %op);
 /// let _guard = span.enter();
 /// // ... do work ...
+///
+/// // Create span with a custom target
+/// let job_span = dyn_span!(target: "jobs", level, "processing", operation = %op);
 /// ```
 ///
 /// # Performance
@@ -81,6 +96,15 @@ macro_rules! dyn_event {
 /// The span creation is still handled by tracing's efficient filtering system.
 #[macro_export]
 macro_rules! dyn_span {
+    (target: $target:expr, $lvl:expr, $($tt:tt)*) => {
+        match $lvl {
+            tracing::Level::ERROR => tracing::span!(target: $target, tracing::Level::ERROR, $($tt)*),
+            tracing::Level::WARN => tracing::span!(target: $target, tracing::Level::WARN, $($tt)*),
+            tracing::Level::INFO => tracing::span!(target: $target, tracing::Level::INFO, $($tt)*),
+            tracing::Level::DEBUG => tracing::span!(target: $target, tracing::Level::DEBUG, $($tt)*),
+            tracing::Level::TRACE => tracing::span!(target: $target, tracing::Level::TRACE, $($tt)*),
+        }
+    };
     ($lvl:expr, $($tt:tt)*) => {
         match $lvl {
             tracing::Level::ERROR => tracing::span!(tracing::Level::ERROR, $($tt)*),
@@ -122,4 +146,11 @@ mod tests {
         let level = Level::INFO;
         dyn_span!(level, "span message");
     }
+
+    #[test]
+    fn test_with_target() {
+        let level = Level::INFO;
+        dyn_event!(target: "access_log", level, status = 200, "access");
+        dyn_span!(target: "access_log", level, "span message");
+    }
 }
@@ -1,8 +1,7 @@
 use crate::extract::http::extract_context_from_headers;
 use opentelemetry::{SpanId, TraceId};
 
-/// The key for the trace id in the span attributes.
-pub const TRACE_ID: &str = "trace_id";
+pub use crate::extract::schema::TRACE_ID;
 
 /// Returns the `trace_id` of the current span according to the global tracing subscriber.
 ///
@@ -200,12 +199,12 @@ mod tests {
         let span = create_span();
         println!(
             "Before set_otel_parent - span trace_id: {}",
-            span.context().span().span_context().trace_id().to_string()
+            span.context().span().span_context().trace_id()
         );
         set_otel_parent(&headers, &span);
         println!(
             "After set_otel_parent - span trace_id: {}",
-            span.context().span().span_context().trace_id().to_string()
+            span.context().span().span_context().trace_id()
         );
 
         // Verify that the trace ID from the header was used
@@ -1,13 +1,40 @@
-use http::{HeaderName, Request};
+use http::{HeaderMap, HeaderName, Request};
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
 
 pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
 pub const REQUEST_ID: HeaderName = HeaderName::from_static("request-id");
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+pub const X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+/// The value recorded for http methods that are not known to the semantic conventions
+pub const OTHER_HTTP_METHOD: &str = "_OTHER";
 
 /// Extract the http method from the request
 pub fn extract_http_method<T>(request: &Request<T>) -> &str {
     request.method().as_str()
 }
 
+/// Normalize the http method to one of the methods known to the semantic conventions.
+///
+/// Unknown methods are mapped to [`OTHER_HTTP_METHOD`] to keep the cardinality bounded.
+pub fn normalize_http_method(method: &http::Method) -> &'static str {
+    match method.as_str() {
+        "CONNECT" => "CONNECT",
+        "DELETE" => "DELETE",
+        "GET" => "GET",
+        "HEAD" => "HEAD",
+        "OPTIONS" => "OPTIONS",
+        "PATCH" => "PATCH",
+        "POST" => "POST",
+        "PUT" => "PUT",
+        "TRACE" => "TRACE",
+        _ => OTHER_HTTP_METHOD,
+    }
+}
+
 /// Extract the http route from the request
 pub fn extract_http_route<T>(request: &http::Request<T>) -> &str {
     request.uri().path()
@@ -18,6 +45,18 @@ pub fn extract_http_version<T>(request: &http::Request<T>) -> http::Version {
     request.version()
 }
 
+/// Extract the network protocol version from the request, e.g. `1.1` or `2`
+pub fn extract_network_protocol_version<T>(request: &http::Request<T>) -> Option<&'static str> {
+    match request.version() {
+        http::Version::HTTP_09 => Some("0.9"),
+        http::Version::HTTP_10 => Some("1.0"),
+        http::Version::HTTP_11 => Some("1.1"),
+        http::Version::HTTP_2 => Some("2"),
+        http::Version::HTTP_3 => Some("3"),
+        _ => None,
+    }
+}
+
 /// Extract the http scheme from the request
 pub fn extract_http_scheme<T>(request: &http::Request<T>) -> Option<&str> {
     request.uri().scheme().map(|s| s.as_str())
@@ -28,6 +67,56 @@ pub fn extract_http_target<T>(request: &http::Request<T>) -> Option<&str> {
     request.uri().path_and_query().map(|s| s.as_str())
 }
 
+/// Extract the url path from the request
+pub fn extract_url_path<T>(request: &http::Request<T>) -> &str {
+    request.uri().path()
+}
+
+/// Extract the url query from the request, without the leading `?`
+pub fn extract_url_query<T>(request: &http::Request<T>) -> Option<&str> {
+    request.uri().query()
+}
+
+/// Extract the server address and port from the request uri or the Host header
+pub fn extract_server_address<T>(request: &http::Request<T>) -> (Option<&str>, Option<u16>) {
+    let authority = request
+        .uri()
+        .authority()
+        .map(|authority| authority.as_str())
+        .or_else(|| extract_host(request));
+    match authority {
+        Some(authority) => {
+            let (host, port) = split_host_port(authority);
+            (Some(host), port)
+        }
+        None => (None, None),
+    }
+}
+
+/// Split an authority such as `example.com:8080` or `[::1]:8080` into host and port
+pub fn split_host_port(authority: &str) -> (&str, Option<u16>) {
+    // Strip any userinfo, it is not part of the server address
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, authority)| authority);
+    if let Some(rest) = authority.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((host, port)) => (
+                host,
+                port.strip_prefix(':').and_then(|port| port.parse().ok()),
+            ),
+            None => (authority, None),
+        };
+    }
+    match authority.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (authority, None),
+        },
+        None => (authority, None),
+    }
+}
+
 /// Extract the user agent from the request headers
 pub fn extract_user_agent<T>(request: &http::Request<T>) -> Option<&str> {
     extract_field_from_headers(request.headers(), http::header::USER_AGENT)
@@ -51,9 +140,228 @@ pub fn extract_request_id_from_headers(headers: &http::HeaderMap) -> Option<&str
         .and_then(|value| value.to_str().ok())
 }
 
-/// Extract the request id from the request headers
+/// Extract the first value of a header from the request headers
+///
+/// Values that are not visible ASCII are skipped, like in [`extract_field_values_from_headers`].
 pub fn extract_field_from_headers(headers: &http::HeaderMap, field: HeaderName) -> Option<&str> {
-    headers.get(field).and_then(|value| value.to_str().ok())
+    extract_field_values_from_headers(headers, field).next()
+}
+
+/// Extract every value of a header from the request headers, in order
+///
+/// Values that are not visible ASCII are skipped.
+pub fn extract_field_values_from_headers(
+    headers: &http::HeaderMap,
+    field: HeaderName,
+) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(field)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+}
+
+/// A range of ip addresses in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`
+///
+/// A single address without a prefix length matches only that address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// Create a new `IpCidr`, returning an error if the prefix is too long for the address
+    pub fn new(addr: IpAddr, prefix: u8) -> anyhow::Result<Self> {
+        let max_prefix = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        anyhow::ensure!(
+            prefix <= max_prefix,
+            "Invalid prefix length {} for {}",
+            prefix,
+            addr
+        );
+        Ok(Self { addr, prefix })
+    }
+
+    /// Returns true if the address is in this range
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid ip address: '{}'", s))?;
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid prefix length: '{}'", s))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(addr, prefix)
+    }
+}
+
+/// The proxies whose forwarding headers are trusted when extracting the client ip
+///
+/// # Example
+///
+/// ```rust
+/// use tracing_otel_extra::extract::fields::TrustedProxies;
+///
+/// let proxies = TrustedProxies::parse(["10.0.0.0/8", "192.168.0.1"]).unwrap();
+/// assert!(proxies.is_trusted("10.1.2.3".parse().unwrap()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    cidrs: Vec<IpCidr>,
+}
+
+impl TrustedProxies {
+    /// Create an empty list, which trusts no proxies
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a list of CIDR ranges
+    pub fn parse<I, S>(cidrs: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = cidrs
+            .into_iter()
+            .map(|cidr| cidr.as_ref().parse())
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { cidrs })
+    }
+
+    /// Add a trusted CIDR range
+    pub fn with_cidr(mut self, cidr: IpCidr) -> Self {
+        self.cidrs.push(cidr);
+        self
+    }
+
+    /// Returns true if the address belongs to a trusted proxy
+    pub fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.cidrs.iter().any(|cidr| cidr.contains(addr))
+    }
+}
+
+/// Extract the client ip, honouring forwarding headers set by trusted proxies
+///
+/// When the socket peer is a trusted proxy, the forwarding chain from the RFC 7239
+/// `Forwarded` header, or the `X-Forwarded-For` header, is walked from the nearest hop
+/// and the first address that is not a trusted proxy is returned. If neither header is
+/// present, the `X-Real-IP` header is used. In all other cases the socket peer is the
+/// client.
+///
+/// Without a socket peer the headers cannot be verified, so `None` is returned.
+pub fn extract_client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &TrustedProxies,
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted_proxies.is_trusted(peer) {
+        return Some(peer);
+    }
+
+    let mut chain = extract_forwarded_for(headers);
+    if chain.is_empty() {
+        chain = extract_x_forwarded_for(headers);
+    }
+    if chain.is_empty() {
+        return extract_field_from_headers(headers, X_REAL_IP)
+            .and_then(parse_node)
+            .or(Some(peer));
+    }
+
+    let mut client = peer;
+    for node in chain.iter().rev() {
+        match node {
+            Some(addr) => {
+                client = *addr;
+                if !trusted_proxies.is_trusted(*addr) {
+                    break;
+                }
+            }
+            // An obfuscated or invalid hop can't be trusted, stop at the last known address
+            None => break,
+        }
+    }
+    Some(client)
+}
+
+/// Extract the `for` addresses from all `Forwarded` headers, from the client to the nearest proxy
+///
+/// Entries that are not ip addresses, such as `unknown` or obfuscated identifiers, are `None`.
+pub fn extract_forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(http::header::FORWARDED)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                key.trim()
+                    .eq_ignore_ascii_case("for")
+                    .then(|| parse_node(value))
+            })
+        })
+        .collect()
+}
+
+/// Extract the addresses from all `X-Forwarded-For` headers, from the client to the nearest proxy
+pub fn extract_x_forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter(|node| !node.trim().is_empty())
+        .map(parse_node)
+        .collect()
+}
+
+/// Parse a forwarded node such as `192.0.2.60`, `"[2001:db8::17]:4711"` or `10.0.0.1:80`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    node.parse::<IpAddr>()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| {
+            node.strip_prefix('[')
+                .and_then(|node| node.strip_suffix(']'))
+                .and_then(|node| node.parse().ok())
+        })
 }
 
 #[cfg(test)]
@@ -116,6 +424,149 @@ mod tests {
         assert_eq!(user_agent, Some("test-user-agent"));
     }
 
+    #[test]
+    fn test_extract_field_values_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append(http::header::ACCEPT, "\u{e9}".parse().unwrap());
+        headers.append(http::header::ACCEPT, "text/html".parse().unwrap());
+        headers.append(http::header::ACCEPT, "application/json".parse().unwrap());
+        let values: Vec<_> =
+            extract_field_values_from_headers(&headers, http::header::ACCEPT).collect();
+        assert_eq!(values, ["text/html", "application/json"]);
+        assert_eq!(
+            extract_field_from_headers(&headers, http::header::ACCEPT),
+            Some("text/html")
+        );
+        assert_eq!(
+            extract_field_from_headers(&headers, http::header::USER_AGENT),
+            None
+        );
+    }
+
+    #[test]
+    fn test_normalize_http_method() {
+        assert_eq!(normalize_http_method(&http::Method::GET), "GET");
+        assert_eq!(normalize_http_method(&http::Method::OPTIONS), "OPTIONS");
+        let method = http::Method::from_bytes(b"PURGE").unwrap();
+        assert_eq!(normalize_http_method(&method), OTHER_HTTP_METHOD);
+    }
+
+    #[test]
+    fn test_extract_server_address() {
+        let request = Request::builder()
+            .header(http::header::HOST, "example.com:8080")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            extract_server_address(&request),
+            (Some("example.com"), Some(8080))
+        );
+
+        let request = Request::builder()
+            .uri("https://[::1]:8443/path")
+            .body(())
+            .unwrap();
+        assert_eq!(extract_server_address(&request), (Some("::1"), Some(8443)));
+
+        let request = Request::builder().body(()).unwrap();
+        assert_eq!(extract_server_address(&request), (None, None));
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port("example.com"), ("example.com", None));
+        assert_eq!(split_host_port("example.com:80"), ("example.com", Some(80)));
+        assert_eq!(split_host_port("[::1]"), ("::1", None));
+        assert_eq!(split_host_port("user@host:1"), ("host", Some(1)));
+    }
+
+    #[test]
+    fn test_extract_url_and_protocol() {
+        let request = Request::builder()
+            .uri("/users?limit=10")
+            .version(http::Version::HTTP_2)
+            .body(())
+            .unwrap();
+        assert_eq!(extract_url_path(&request), "/users");
+        assert_eq!(extract_url_query(&request), Some("limit=10"));
+        assert_eq!(extract_network_protocol_version(&request), Some("2"));
+    }
+
+    #[test]
+    fn test_ip_cidr() {
+        let cidr: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.0.0.1".parse().unwrap()));
+
+        let cidr: IpCidr = "fd00::/8".parse().unwrap();
+        assert!(cidr.contains("fd12::1".parse().unwrap()));
+        assert!(!cidr.contains("10.0.0.1".parse().unwrap()));
+
+        let cidr: IpCidr = "0.0.0.0/0".parse().unwrap();
+        assert!(cidr.contains("1.2.3.4".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("not-an-ip".parse::<IpCidr>().is_err());
+    }
+
+    #[test]
+    fn test_extract_client_ip_untrusted_peer() {
+        let trusted = TrustedProxies::parse(["10.0.0.0/8"]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, "1.1.1.1".parse().unwrap());
+        let peer = "8.8.8.8".parse().ok();
+        assert_eq!(extract_client_ip(&headers, peer, &trusted), peer);
+        assert_eq!(extract_client_ip(&headers, None, &trusted), None);
+    }
+
+    #[test]
+    fn test_extract_client_ip_x_forwarded_for() {
+        let trusted = TrustedProxies::parse(["10.0.0.0/8"]).unwrap();
+        let mut headers = HeaderMap::new();
+        // The spoofed first entry is ignored, the first untrusted hop from the right wins
+        headers.insert(
+            X_FORWARDED_FOR,
+            "6.6.6.6, 1.1.1.1, 10.0.0.2".parse().unwrap(),
+        );
+        let peer = "10.0.0.1".parse().ok();
+        assert_eq!(
+            extract_client_ip(&headers, peer, &trusted),
+            "1.1.1.1".parse().ok()
+        );
+    }
+
+    #[test]
+    fn test_extract_client_ip_forwarded() {
+        let trusted = TrustedProxies::parse(["10.0.0.0/8"]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::FORWARDED,
+            r#"for="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.2;by=10.0.0.1"#
+                .parse()
+                .unwrap(),
+        );
+        headers.insert(X_FORWARDED_FOR, "1.1.1.1".parse().unwrap());
+        let peer = "10.0.0.1".parse().ok();
+        assert_eq!(
+            extract_client_ip(&headers, peer, &trusted),
+            "2001:db8:cafe::17".parse().ok()
+        );
+    }
+
+    #[test]
+    fn test_extract_client_ip_x_real_ip() {
+        let trusted = TrustedProxies::parse(["10.0.0.0/8"]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(X_REAL_IP, "1.1.1.1".parse().unwrap());
+        let peer = "10.0.0.1".parse().ok();
+        assert_eq!(
+            extract_client_ip(&headers, peer, &trusted),
+            "1.1.1.1".parse().ok()
+        );
+        assert_eq!(extract_client_ip(&HeaderMap::new(), peer, &trusted), peer);
+    }
+
     #[test]
     fn test_extract_host() {
         let request = Request::builder()
@@ -4,5 +4,7 @@ pub mod context;
 pub mod fields;
 #[cfg(feature = "http")]
 pub mod http;
+#[cfg(feature = "fields")]
+pub mod schema;
 #[cfg(feature = "span")]
 pub mod span;
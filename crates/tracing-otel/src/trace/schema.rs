@@ -0,0 +1,86 @@
+//! Field names shared by the request spans of this crate and `axum-otel`.
+//!
+//! Spans created with [`request_span!`](crate::request_span) declare every legacy field below,
+//! so recorders such as [`set_otel_parent`](crate::extract::context::set_otel_parent) can rely
+//! on them regardless of which crate created the span.
+//!
+//! The fields of the stable HTTP semantic conventions, from [`HTTP_REQUEST_METHOD`] on, are
+//! only declared by spans that record those conventions.
+
+/// The address of the client, from trusted proxy headers or the peer address.
+pub const HTTP_CLIENT_IP: &str = "http.client_ip";
+
+/// The HTTP version of the request.
+pub const HTTP_VERSION: &str = "http.version";
+
+/// The `host` header of the request.
+pub const HTTP_HOST: &str = "http.host";
+
+/// The HTTP method of the request.
+pub const HTTP_METHOD: &str = "http.method";
+
+/// The matched route template, e.g. `/users/{id}`.
+pub const HTTP_ROUTE: &str = "http.route";
+
+/// The URI scheme of the request.
+pub const HTTP_SCHEME: &str = "http.scheme";
+
+/// The HTTP status code of the response.
+pub const HTTP_STATUS_CODE: &str = "http.status_code";
+
+/// The path and query of the request.
+pub const HTTP_TARGET: &str = "http.target";
+
+/// The `user-agent` header of the request.
+pub const HTTP_USER_AGENT: &str = "http.user_agent";
+
+/// The name of the OpenTelemetry span.
+pub const OTEL_NAME: &str = "otel.name";
+
+/// The kind of the OpenTelemetry span.
+pub const OTEL_KIND: &str = "otel.kind";
+
+/// The status code of the OpenTelemetry span, `OK` or `ERROR`.
+pub const OTEL_STATUS_CODE: &str = "otel.status_code";
+
+/// The status message of the OpenTelemetry span.
+pub const OTEL_STATUS_MESSAGE: &str = "otel.status_message";
+
+/// The id of the request.
+pub const REQUEST_ID: &str = "request_id";
+
+/// The id of the trace the request belongs to.
+pub const TRACE_ID: &str = "trace_id";
+
+/// The HTTP method of the request, `_OTHER` for methods unknown to the specification.
+pub const HTTP_REQUEST_METHOD: &str = "http.request.method";
+
+/// The original HTTP method of the request, when it was recorded as `_OTHER`.
+pub const HTTP_REQUEST_METHOD_ORIGINAL: &str = "http.request.method_original";
+
+/// The HTTP status code of the response, in the stable conventions.
+pub const HTTP_RESPONSE_STATUS_CODE: &str = "http.response.status_code";
+
+/// The path of the request.
+pub const URL_PATH: &str = "url.path";
+
+/// The query of the request, without the leading `?`.
+pub const URL_QUERY: &str = "url.query";
+
+/// The URI scheme of the request, in the stable conventions.
+pub const URL_SCHEME: &str = "url.scheme";
+
+/// The address of the client, in the stable conventions.
+pub const CLIENT_ADDRESS: &str = "client.address";
+
+/// The host the request was sent to.
+pub const SERVER_ADDRESS: &str = "server.address";
+
+/// The port the request was sent to.
+pub const SERVER_PORT: &str = "server.port";
+
+/// The HTTP version of the request, e.g. `1.1`.
+pub const NETWORK_PROTOCOL_VERSION: &str = "network.protocol.version";
+
+/// The `user-agent` header of the request, in the stable conventions.
+pub const USER_AGENT_ORIGINAL: &str = "user_agent.original";
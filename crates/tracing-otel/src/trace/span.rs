@@ -1,9 +1,76 @@
-use crate::{
-    dyn_span,
-    extract::{context, fields},
-};
+use crate::extract::{context, fields, schema};
 use http::Request;
-use tracing::{field::Empty, Level, Span};
+use tracing::{field::debug, Level, Span};
+
+/// Creates a request span that declares every field of the [`schema`] module.
+///
+/// The span is named `request`, `otel.name` is set to the given name and `otel.kind` to
+/// `server`, the other schema fields are left empty to be recorded later. Extra fields can be
+/// declared after the name, as long as the span stays within the 32 fields `tracing` supports.
+///
+/// # Example
+///
+/// ```rust
+/// use tracing::Level;
+/// use tracing_otel_extra::{extract::schema, request_span};
+///
+/// let span = request_span!(Level::INFO, "GET /users", tenant.id = "acme");
+/// span.record(schema::HTTP_STATUS_CODE, 200);
+/// ```
+#[macro_export]
+macro_rules! request_span {
+    ($lvl:expr, $name:expr) => {
+        $crate::request_span!($lvl, $name,)
+    };
+    ($lvl:expr, $name:expr, $($fields:tt)*) => {
+        $crate::dyn_span!(
+            $lvl,
+            "request",
+            { $crate::extract::schema::HTTP_CLIENT_IP } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_VERSION } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_HOST } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_METHOD } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_ROUTE } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_SCHEME } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_STATUS_CODE } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_TARGET } = tracing::field::Empty,
+            { $crate::extract::schema::HTTP_USER_AGENT } = tracing::field::Empty,
+            { $crate::extract::schema::OTEL_NAME } = $name,
+            { $crate::extract::schema::OTEL_KIND } = "server",
+            { $crate::extract::schema::OTEL_STATUS_CODE } = tracing::field::Empty,
+            { $crate::extract::schema::OTEL_STATUS_MESSAGE } = tracing::field::Empty,
+            { $crate::extract::schema::REQUEST_ID } = tracing::field::Empty,
+            { $crate::extract::schema::TRACE_ID } = tracing::field::Empty,
+            $($fields)*
+        )
+    };
+}
+
+/// Records the HTTP fields of the [`schema`] that are known from the request alone.
+///
+/// Fields missing from the request, like an absent `user-agent` header, are left unset.
+pub fn record_http_fields<B>(span: &Span, request: &Request<B>) {
+    if span.is_disabled() {
+        return;
+    }
+    // The `Debug` output of a version has no quotes, e.g. `HTTP/1.1`
+    span.record(
+        schema::HTTP_VERSION,
+        debug(fields::extract_http_version(request)),
+    );
+    span.record(schema::HTTP_METHOD, fields::extract_http_method(request));
+    let optional_fields = [
+        (schema::HTTP_HOST, fields::extract_host(request)),
+        (schema::HTTP_SCHEME, fields::extract_http_scheme(request)),
+        (schema::HTTP_TARGET, fields::extract_http_target(request)),
+        (schema::HTTP_USER_AGENT, fields::extract_user_agent(request)),
+    ];
+    for (field, value) in optional_fields {
+        if let Some(value) = value {
+            span.record(field, value);
+        }
+    }
+}
 
 /// Creates a new [`Span`] for the given request.
 /// you can use this span to record the request and response
@@ -11,7 +78,7 @@ use tracing::{field::Empty, Level, Span};
 /// # Example
 ///
 /// ```rust
-/// use tracing_otel_extra::extract::span::make_request_span;
+/// use tracing_otel_extra::extract::{schema, span::make_request_span};
 /// use tracing::Level;
 /// use http::Request;
 ///
@@ -21,36 +88,77 @@ use tracing::{field::Empty, Level, Span};
 ///     .body(())
 ///     .unwrap();
 /// let span = make_request_span(Level::INFO, &request);
-/// span.record("http.method", "GET");
-/// span.record("http.route", "GET /");
-/// span.record("http.status", 200);
-/// span.record("http.user_agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36");
-/// span.record("otel.name", "request");
-/// span.record("otel.kind", "server");
-/// span.record("otel.status", "ok");
-/// span.record("request_id", "1234567890");
+/// span.record(schema::HTTP_ROUTE, "/");
+/// span.record(schema::HTTP_STATUS_CODE, 200);
+/// span.record(schema::OTEL_STATUS_CODE, "OK");
 /// ```
 pub fn make_request_span<B>(level: Level, request: &Request<B>) -> Span {
-    let span = dyn_span!(
-        level,
-        "request",
-        // HTTP fields
-        http.version = ?fields::extract_http_version(request),
-        http.host = ?fields::extract_host(request),
-        http.method = ?fields::extract_http_method(request),
-        http.route = Empty,
-        http.scheme = ?fields::extract_http_scheme(request).map(debug),
-        http.status = Empty,
-        http.target = ?fields::extract_http_target(request),
-        http.user_agent = ?fields::extract_user_agent(request),
-        // OpenTelemetry fields
-        otel.name = Empty,
-        otel.kind = ?Empty,
-        otel.status = Empty,
-        // Request tracking
-        request.id = %fields::extract_request_id(request),
-        trace.id = Empty
-    );
+    let span = request_span!(level, fields::extract_http_method(request));
+    record_http_fields(&span, request);
+    if let Some(request_id) = fields::extract_request_id_from_headers(request.headers()) {
+        span.record(schema::REQUEST_ID, request_id);
+    }
     context::set_otel_parent(request.headers(), &span);
     span
 }
+
+#[cfg(test)]
+#[cfg(feature = "span")]
+mod tests {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span::{Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+    /// Collects the fields recorded on spans after their creation.
+    #[derive(Clone, Default)]
+    struct Recorded(Arc<Mutex<HashMap<&'static str, String>>>);
+
+    impl<S: Subscriber> Layer<S> for Recorded {
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            struct Visitor<'a>(&'a mut HashMap<&'static str, String>);
+            impl Visit for Visitor<'_> {
+                fn record_str(&mut self, field: &Field, value: &str) {
+                    self.0.insert(field.name(), value.to_owned());
+                }
+
+                fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                    self.0.insert(field.name(), format!("{:?}", value));
+                }
+            }
+            values.record(&mut Visitor(&mut self.0.lock().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_make_request_span_records_schema_fields() {
+        let recorded = Recorded::default();
+        let subscriber = tracing_subscriber::registry().with(recorded.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let request = Request::get("https://example.com/users?page=2")
+            .header("x-request-id", "1234")
+            .body(())
+            .unwrap();
+        let span = make_request_span(Level::INFO, &request);
+        span.record(schema::HTTP_STATUS_CODE, 200);
+
+        let recorded = recorded.0.lock().unwrap();
+        assert_eq!(recorded[schema::HTTP_VERSION], "HTTP/1.1");
+        assert_eq!(recorded[schema::HTTP_METHOD], "GET");
+        assert_eq!(recorded[schema::HTTP_SCHEME], "https");
+        assert_eq!(recorded[schema::HTTP_TARGET], "/users?page=2");
+        assert!(!recorded.contains_key(schema::HTTP_HOST));
+        assert!(!recorded.contains_key(schema::HTTP_USER_AGENT));
+        assert_eq!(recorded[schema::HTTP_STATUS_CODE], "200");
+        assert_eq!(recorded[schema::REQUEST_ID], "1234");
+        assert!(recorded.contains_key(schema::TRACE_ID));
+    }
+}
@@ -4,14 +4,11 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use axum_otel::{AxumOtelOnFailure, AxumOtelOnResponse, AxumOtelSpanCreator};
+use axum_otel::AxumOtelRouterExt;
 
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tower::ServiceBuilder;
-use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
-use tower_http::trace::TraceLayer;
 use tracing_otel_extra::Logger;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -79,17 +76,7 @@ async fn main() -> Result<()> {
         .route("/users", get(get_users))
         .route("/users/{id}", get(get_user))
         .route("/users", post(create_user))
-        .layer(
-            ServiceBuilder::new()
-                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-                .layer(
-                    TraceLayer::new_for_http()
-                        .make_span_with(AxumOtelSpanCreator::new())
-                        .on_response(AxumOtelOnResponse::new())
-                        .on_failure(AxumOtelOnFailure::new()),
-                )
-                .layer(PropagateRequestIdLayer::x_request_id()),
-        )
+        .with_otel()
         .with_state(state);
 
     let listener = TcpListener::bind("127.0.0.1:8081").await?;
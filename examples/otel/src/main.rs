@@ -1,12 +1,9 @@
 use anyhow::Result;
 use axum::extract::Query;
 use axum::{routing::get, Router};
-use axum_otel::{AxumOtelOnFailure, AxumOtelOnResponse, AxumOtelSpanCreator, Level};
+use axum_otel::{AxumOtelLayer, Level, RequestFilter};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
-use tower::ServiceBuilder;
-use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
-use tower_http::trace::TraceLayer;
 use tracing::{debug, info};
 use tracing_otel_extra::Logger;
 
@@ -40,18 +37,15 @@ async fn main() -> Result<()> {
     // Setup Axum router and server
     let app = Router::new()
         .route("/hello", get(hello))
+        .route("/health", get(health))
         .layer(
-            ServiceBuilder::new()
-                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
-                .layer(
-                    TraceLayer::new_for_http()
-                        .make_span_with(AxumOtelSpanCreator::new().level(Level::INFO))
-                        .on_response(AxumOtelOnResponse::new().level(Level::INFO))
-                        .on_failure(AxumOtelOnFailure::new().level(Level::ERROR)),
-                )
-                .layer(PropagateRequestIdLayer::x_request_id()),
-        )
-        .route("/health", get(health)); // without request id, the span will not be created
+            AxumOtelLayer::new()
+                .span_level(Level::INFO)
+                .response_level(Level::INFO)
+                .failure_level(Level::ERROR)
+                .metrics(true)
+                .filter(RequestFilter::new().path("/health")),
+        );
 
     let listener = TcpListener::bind("127.0.0.1:8080").await?;
     info!("Server is running on http://127.0.0.1:8080");